@@ -0,0 +1,300 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash::hashv, instruction::Instruction},
+};
+
+use crate::{
+    constants::{ed25519program, secp256r1program, signing_authority},
+    errors::BonkPawsError,
+};
+
+/*
+
+    Donation Approvals
+
+    An approval is a 72 byte message signed by our approval service:
+
+    - Charity ID (u64, little endian)
+    - Charity address
+    - Match key (default pubkey if the donation should not be matched)
+
+    It can be verified by either the ed25519 or the secp256r1 precompile,
+    in which case we enforce a strict single signature header so that the
+    public key, signature and message are all read from the precompile
+    instruction itself.
+
+    Alternatively, the same tuple can be proven against the Merkle root of
+    our vetted charity list stored in the config, which removes the need
+    for a live signature from the approval service.
+
+*/
+
+pub const APPROVAL_MESSAGE_LEN: usize = 8 + 32 + 32;
+
+const ED25519_HEADER: [u8; 16] = [0x01, 0x00, 0x30, 0x00, 0xff, 0xff, 0x10, 0x00, 0xff, 0xff, 0x70, 0x00, 0x48, 0x00, 0xff, 0xff];
+const ED25519_MESSAGE_OFFSET: usize = 0x70;
+
+const SECP256R1_HEADER: [u8; 16] = [0x01, 0x00, 0x31, 0x00, 0xff, 0xff, 0x10, 0x00, 0xff, 0xff, 0x71, 0x00, 0x48, 0x00, 0xff, 0xff];
+const SECP256R1_MESSAGE_OFFSET: usize = 0x71;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AllowlistProof {
+    pub id: u64,
+    pub match_key: Pubkey,
+    pub proof: Vec<[u8; 32]>,
+}
+
+pub struct Approval {
+    pub id: u64,
+    pub charity: Pubkey,
+    pub match_key: Pubkey,
+}
+
+impl Approval {
+    pub fn try_from_ed25519_ix(ix: &Instruction) -> Result<Self> {
+        require_keys_eq!(ed25519program::ID, ix.program_id, BonkPawsError::ProgramMismatch);
+        require_eq!(ix.data.len(), ED25519_MESSAGE_OFFSET + APPROVAL_MESSAGE_LEN, BonkPawsError::SignatureHeaderMismatch);
+
+        // Ensure a strict instruction header format
+        require!(ED25519_HEADER.eq(&ix.data[0..16]), BonkPawsError::SignatureHeaderMismatch);
+
+        // Ensure signing authority is correct
+        require!(signing_authority::ID.to_bytes().eq(&ix.data[16..48]), BonkPawsError::SignatureAuthorityMismatch);
+
+        Ok(Self::from_message(&ix.data[ED25519_MESSAGE_OFFSET..]))
+    }
+
+    pub fn try_from_secp256r1_ix(ix: &Instruction, authority: &[u8; 33]) -> Result<Self> {
+        require_keys_eq!(secp256r1program::ID, ix.program_id, BonkPawsError::ProgramMismatch);
+        require_eq!(ix.data.len(), SECP256R1_MESSAGE_OFFSET + APPROVAL_MESSAGE_LEN, BonkPawsError::SignatureHeaderMismatch);
+
+        // Ensure a strict instruction header format
+        require!(SECP256R1_HEADER.eq(&ix.data[0..16]), BonkPawsError::SignatureHeaderMismatch);
+
+        // Ensure the compressed P-256 signing authority is correct
+        require!(authority.eq(&ix.data[16..49]), BonkPawsError::SignatureAuthorityMismatch);
+
+        Ok(Self::from_message(&ix.data[SECP256R1_MESSAGE_OFFSET..]))
+    }
+
+    pub fn try_from_allowlist_proof(allowlist_proof: AllowlistProof, charity: Pubkey, merkle_root: &[u8; 32]) -> Result<Self> {
+        require!(merkle_root.ne(&[0u8; 32]), BonkPawsError::AllowlistDisabled);
+
+        let AllowlistProof { id, match_key, proof } = allowlist_proof;
+
+        // Leaves and nodes are domain separated to prevent second preimage attacks
+        let leaf = hashv(&[&[0x00], &id.to_le_bytes(), charity.as_ref(), match_key.as_ref()]).to_bytes();
+
+        // Sibling pairs are sorted so that proofs don't need to encode a path
+        let root = proof.iter().fold(leaf, |node, sibling| {
+            match node <= *sibling {
+                true => hashv(&[&[0x01], &node, sibling]).to_bytes(),
+                false => hashv(&[&[0x01], sibling, &node]).to_bytes(),
+            }
+        });
+        require!(root.eq(merkle_root), BonkPawsError::InvalidMerkleProof);
+
+        Ok(Self { id, charity, match_key })
+    }
+
+    fn from_message(message: &[u8]) -> Self {
+        // The following fetches the id for usage in the transaction history
+        let mut id_data: [u8; 8] = [0u8; 8];
+        id_data.copy_from_slice(&message[0x00..0x08]);
+
+        // The following fetches the charity key for later verification
+        let mut charity_data: [u8; 32] = [0u8; 32];
+        charity_data.copy_from_slice(&message[0x08..0x28]);
+
+        // The following fetches the match key for later verification
+        let mut match_key_data: [u8; 32] = [0u8; 32];
+        match_key_data.copy_from_slice(&message[0x28..0x48]);
+
+        Self {
+            id: u64::from_le_bytes(id_data),
+            charity: Pubkey::from(charity_data),
+            match_key: Pubkey::from(match_key_data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUTHORITY: [u8; 33] = [0x02; 33];
+
+    fn message(id: u64, charity: &Pubkey, match_key: &Pubkey) -> Vec<u8> {
+        [&id.to_le_bytes()[..], charity.as_ref(), match_key.as_ref()].concat()
+    }
+
+    fn secp256r1_ix(authority: &[u8; 33], message: &[u8]) -> Instruction {
+        Instruction {
+            program_id: secp256r1program::ID,
+            accounts: vec![],
+            data: [&SECP256R1_HEADER[..], authority, &[0u8; 64], message].concat(),
+        }
+    }
+
+    #[test]
+    fn secp256r1_approval_is_parsed() {
+        let (charity, match_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ix = secp256r1_ix(&AUTHORITY, &message(42, &charity, &match_key));
+
+        let approval = Approval::try_from_secp256r1_ix(&ix, &AUTHORITY).unwrap();
+        assert_eq!(approval.id, 42);
+        assert_eq!(approval.charity, charity);
+        assert_eq!(approval.match_key, match_key);
+    }
+
+    #[test]
+    fn secp256r1_approval_requires_configured_authority() {
+        let ix = secp256r1_ix(&[0x03; 33], &message(42, &Pubkey::new_unique(), &Pubkey::new_unique()));
+
+        assert_eq!(
+            Approval::try_from_secp256r1_ix(&ix, &AUTHORITY).err(),
+            Some(BonkPawsError::SignatureAuthorityMismatch.into())
+        );
+    }
+
+    #[test]
+    fn secp256r1_approval_requires_strict_header() {
+        let mut ix = secp256r1_ix(&AUTHORITY, &message(42, &Pubkey::new_unique(), &Pubkey::new_unique()));
+        // Point the signature at another instruction
+        ix.data[4] = 0x00;
+
+        assert_eq!(
+            Approval::try_from_secp256r1_ix(&ix, &AUTHORITY).err(),
+            Some(BonkPawsError::SignatureHeaderMismatch.into())
+        );
+    }
+
+    #[test]
+    fn secp256r1_approval_requires_message_length() {
+        let ix = secp256r1_ix(&AUTHORITY, &message(42, &Pubkey::new_unique(), &Pubkey::new_unique())[1..]);
+
+        assert_eq!(
+            Approval::try_from_secp256r1_ix(&ix, &AUTHORITY).err(),
+            Some(BonkPawsError::SignatureHeaderMismatch.into())
+        );
+    }
+
+    #[test]
+    fn secp256r1_approval_requires_precompile() {
+        let mut ix = secp256r1_ix(&AUTHORITY, &message(42, &Pubkey::new_unique(), &Pubkey::new_unique()));
+        ix.program_id = ed25519program::ID;
+
+        assert_eq!(
+            Approval::try_from_secp256r1_ix(&ix, &AUTHORITY).err(),
+            Some(BonkPawsError::ProgramMismatch.into())
+        );
+    }
+
+    fn leaf(id: u64, charity: &Pubkey, match_key: &Pubkey) -> [u8; 32] {
+        hashv(&[&[0x00], &id.to_le_bytes(), charity.as_ref(), match_key.as_ref()]).to_bytes()
+    }
+
+    fn node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        match a <= b {
+            true => hashv(&[&[0x01], a, b]).to_bytes(),
+            false => hashv(&[&[0x01], b, a]).to_bytes(),
+        }
+    }
+
+    // A four leaf tree, along with each leaf's charity and proof
+    fn tree() -> ([u8; 32], Vec<(Pubkey, AllowlistProof)>) {
+        let charities: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let leaves: Vec<[u8; 32]> = charities.iter().enumerate().map(|(id, charity)| leaf(id as u64, charity, &Pubkey::default())).collect();
+        let (left, right) = (node(&leaves[0], &leaves[1]), node(&leaves[2], &leaves[3]));
+
+        let proofs = [
+            vec![leaves[1], right],
+            vec![leaves[0], right],
+            vec![leaves[3], left],
+            vec![leaves[2], left],
+        ];
+        let leaves = charities
+            .into_iter()
+            .zip(proofs)
+            .enumerate()
+            .map(|(id, (charity, proof))| (charity, AllowlistProof { id: id as u64, match_key: Pubkey::default(), proof }))
+            .collect();
+
+        (node(&left, &right), leaves)
+    }
+
+    #[test]
+    fn allowlist_proof_is_verified_for_every_leaf() {
+        let (root, leaves) = tree();
+
+        for (charity, allowlist_proof) in leaves {
+            let id = allowlist_proof.id;
+            let approval = Approval::try_from_allowlist_proof(allowlist_proof, charity, &root).unwrap();
+            assert_eq!(approval.id, id);
+            assert_eq!(approval.charity, charity);
+            assert_eq!(approval.match_key, Pubkey::default());
+        }
+    }
+
+    #[test]
+    fn allowlist_proof_of_a_single_leaf_is_empty() {
+        let charity = Pubkey::new_unique();
+        let match_key = Pubkey::new_unique();
+        let root = leaf(1, &charity, &match_key);
+
+        assert!(Approval::try_from_allowlist_proof(AllowlistProof { id: 1, match_key, proof: vec![] }, charity, &root).is_ok());
+    }
+
+    #[test]
+    fn allowlist_proof_must_be_in_order() {
+        let (root, mut leaves) = tree();
+        let (charity, mut allowlist_proof) = leaves.remove(0);
+        allowlist_proof.proof.reverse();
+
+        assert_eq!(
+            Approval::try_from_allowlist_proof(allowlist_proof, charity, &root).err(),
+            Some(BonkPawsError::InvalidMerkleProof.into())
+        );
+    }
+
+    #[test]
+    fn allowlist_proof_rejects_tampered_sibling() {
+        let (root, mut leaves) = tree();
+        let (charity, mut allowlist_proof) = leaves.remove(2);
+        allowlist_proof.proof[0][0] ^= 0x01;
+
+        assert_eq!(
+            Approval::try_from_allowlist_proof(allowlist_proof, charity, &root).err(),
+            Some(BonkPawsError::InvalidMerkleProof.into())
+        );
+    }
+
+    #[test]
+    fn allowlist_proof_binds_the_approval() {
+        let (root, mut leaves) = tree();
+        let (charity, AllowlistProof { id, proof, .. }) = leaves.remove(1);
+
+        // Another charity, id or match key changes the leaf
+        for (charity, id, match_key) in [
+            (Pubkey::new_unique(), id, Pubkey::default()),
+            (charity, id + 1, Pubkey::default()),
+            (charity, id, Pubkey::new_unique()),
+        ] {
+            assert_eq!(
+                Approval::try_from_allowlist_proof(AllowlistProof { id, match_key, proof: proof.clone() }, charity, &root).err(),
+                Some(BonkPawsError::InvalidMerkleProof.into())
+            );
+        }
+    }
+
+    #[test]
+    fn allowlist_is_disabled_without_a_root() {
+        let (_, mut leaves) = tree();
+        let (charity, allowlist_proof) = leaves.remove(0);
+
+        assert_eq!(
+            Approval::try_from_allowlist_proof(allowlist_proof, charity, &[0u8; 32]).err(),
+            Some(BonkPawsError::AllowlistDisabled.into())
+        );
+    }
+}
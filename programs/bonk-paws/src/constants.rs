@@ -1,8 +1,17 @@
+pub const MAX_SLIPPAGE_BPS: u16 = 50;
 pub const MIN_MATCH_THRESHOLD: u64 = 0;
 pub const MAX_MATCH_THRESHOLD: u64 = 1_000_000_000_000_000;
 pub const MIN_BURN_THRESHOLD: u64 = 100_000_000;
 pub const MAX_BURN_THRESHOLD: u64 = 1_000_000_000_000_000;
 pub const BURN_DENOMINATOR: u16 = 100;
+pub const MATCH_RATIO_DENOMINATOR: u64 = 10_000;
+pub const MAX_MATCH_RATIO_BPS: u16 = 30_000;
+pub const MAX_CAMPAIGN_CHARITIES: usize = 16;
+pub const MAX_VAULT_MINTS: usize = 4;
+pub const MAX_POOLS: usize = 4;
+pub const BONK_BURN_BPS: u16 = 100;
+pub const SECONDS_PER_DAY: i64 = 86_400;
+pub const PRICE_PRECISION: u128 = 1_000_000_000;
 
 use anchor_lang::declare_id;
 
@@ -24,4 +33,9 @@ pub mod wsol {
 pub mod ed25519program {
     use super::*;
     declare_id!("Ed25519SigVerify111111111111111111111111111");
+}
+
+pub mod secp256r1program {
+    use super::*;
+    declare_id!("Secp256r1SigVerify1111111111111111111111111");
 }
\ No newline at end of file
@@ -0,0 +1,562 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::{
+        self,
+        instructions::{load_current_index_checked, load_instruction_at_checked},
+    },
+    system_program::{allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer},
+    Discriminator,
+};
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{Mint, Token, TokenAccount},
+};
+
+use crate::{
+    constants::wsol,
+    contexts::{Settlement, SwapAccounts},
+    errors::BonkPawsError,
+    programs::swap_venue,
+    require_instruction_eq,
+    state::{Config, DonationState, MatchDonationState, MatchRecord, MatchStatus, SurplusPolicy, VaultMintState},
+};
+
+/*
+
+    Batched Matches
+
+    Rather than a match/swap/finalize triple per donation, a batch matches
+    many donations with a single ExactOut swap for their summed targets,
+    saving on swap fees and price impact:
+
+    - match_batch takes the match donation states as remaining accounts,
+      in strictly ascending key order so none can be matched twice
+    - finalize_batch takes the same states in the same order, each followed
+      by its match key, donor and match record
+
+*/
+
+#[derive(Accounts)]
+pub struct MatchBatch<'info> {
+    #[account(mut)]
+    signer: Signer<'info>,
+    #[account(
+        constraint = config.vault_mint(&mint.key()).is_ok() @ BonkPawsError::InvalidVaultMint
+    )]
+    mint: Account<'info, Mint>,
+    #[account(
+        address = wsol::ID
+    )]
+    wsol: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = wsol,
+        associated_token::authority = signer,
+    )]
+    signer_wsol: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+    #[account(
+        seeds = [b"donation_state"],
+        bump,
+    )]
+    donation_state: Account<'info, DonationState>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = donation_state,
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(address = config.vault_mint(&mint.key())?.price_account)]
+    /// CHECK: Pyth style price account of the mint in SOL, parsed in the handler
+    oracle: Option<UncheckedAccount<'info>>,
+    #[account(address = sysvar::instructions::ID)]
+    /// CHECK: InstructionsSysvar account
+    instructions: UncheckedAccount<'info>,
+    associated_token_program: Program<'info, AssociatedToken>,
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> MatchBatch<'info> {
+    pub fn match_batch(&mut self, match_donation_states: &'info [AccountInfo<'info>], bumps: MatchBatchBumps) -> Result<()> {
+        require!(!match_donation_states.is_empty(), BonkPawsError::InvalidBatch);
+
+        // Disable CPIs
+        let ixs = self.instructions.to_account_info();
+        let current_index = load_current_index_checked(&ixs)? as usize;
+        let current_ix = load_instruction_at_checked(current_index, &ixs)?;
+        require!(crate::check_id(&current_ix.program_id), BonkPawsError::ProgramMismatch);
+
+        // Match Swap Instruction, which must buy exactly the summed targets
+        let swap_ix = load_instruction_at_checked(current_index + 1, &ixs).map_err(|_| BonkPawsError::MissingSwapIx)?;
+        let swap = swap_venue(&swap_ix)?;
+        self.swap_accounts().validate(swap.as_ref())?;
+        require!(!swap.is_exact_in(), BonkPawsError::InvalidRoute);
+
+        require_ascending_keys(match_donation_states.iter().map(|account| account.key()))?;
+
+        let mut target_lamports: u64 = 0;
+        for account in match_donation_states {
+            require!(account.is_writable, BonkPawsError::InvalidBatch);
+
+            let mut match_donation_state = Account::<MatchDonationState>::try_from(account)?;
+            target_lamports = target_lamports
+                .checked_add(match_donation_state.target_lamports)
+                .ok_or(BonkPawsError::Overflow)?;
+
+            match_donation_state.pre_match_vault_balance = self.vault.amount;
+            match_donation_state.transition(MatchStatus::Swapping)?;
+            match_donation_state.exit(&crate::ID)?;
+        }
+        require_eq!(swap.min_out_amount()?, target_lamports, BonkPawsError::InvalidSolanaAmount);
+
+        self.swap_accounts().approve(swap.max_in_amount()?, bumps.donation_state)?;
+
+        /*
+
+            Match Finalize Batch Instruction
+
+            The same keeper must finalize every state in the batch, in the
+            same order, so that none is left mid swap.
+
+        */
+
+        let ix = load_instruction_at_checked(current_index.checked_add(2).ok_or(BonkPawsError::Overflow)?, &ixs)
+            .map_err(|_| BonkPawsError::MissingFinalizeIx)?;
+        require_instruction_eq!(ix, crate::ID, crate::instruction::FinalizeBatch::DISCRIMINATOR, BonkPawsError::InvalidInstruction);
+        require_keys_eq!(
+            ix.accounts.get(FinalizeBatch::SIGNER_INDEX).ok_or(BonkPawsError::UnauthorizedKeeper)?.pubkey,
+            self.signer.key(),
+            BonkPawsError::UnauthorizedKeeper
+        );
+
+        let finalized_states = ix.accounts
+            .get(FinalizeBatch::ACCOUNT_COUNT..)
+            .ok_or(BonkPawsError::InvalidBatch)?;
+        require_eq!(
+            finalized_states.len(),
+            match_donation_states.len().checked_mul(FinalizeBatch::ACCOUNTS_PER_MATCH).ok_or(BonkPawsError::Overflow)?,
+            BonkPawsError::InvalidBatch
+        );
+        for (account, finalized) in match_donation_states.iter().zip(finalized_states.chunks(FinalizeBatch::ACCOUNTS_PER_MATCH)) {
+            require_keys_eq!(account.key(), finalized[0].pubkey, BonkPawsError::InvalidBatch);
+        }
+
+        Ok(())
+    }
+
+    fn swap_accounts(&self) -> SwapAccounts<'_, 'info> {
+        SwapAccounts {
+            signer: &self.signer,
+            mint: &self.mint,
+            wsol: &self.wsol,
+            signer_wsol: &self.signer_wsol,
+            config: &self.config,
+            donation_state: &self.donation_state,
+            vault: &self.vault,
+            oracle: self.oracle.as_ref(),
+            token_program: &self.token_program,
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct FinalizeBatch<'info> {
+    #[account(mut)]
+    signer: Signer<'info>,
+    #[account(mut)]
+    treasury: Option<SystemAccount<'info>>,
+    #[account(
+        mut,
+        constraint = config.vault_mint(&mint.key()).is_ok() @ BonkPawsError::InvalidVaultMint
+    )]
+    mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = donation_state,
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        seeds = [b"vault_mint", mint.key().as_ref()],
+        bump,
+        space = VaultMintState::INIT_SPACE
+    )]
+    vault_mint_state: Account<'info, VaultMintState>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = signer,
+    )]
+    keeper_token: Option<Account<'info, TokenAccount>>,
+    #[account(
+        address = wsol::ID
+    )]
+    wsol: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = wsol,
+        associated_token::authority = signer,
+    )]
+    signer_wsol: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = wsol,
+        associated_token::authority = donation_state,
+    )]
+    wsol_vault: Option<Account<'info, TokenAccount>>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"donation_state"],
+        bump,
+    )]
+    donation_state: Account<'info, DonationState>,
+    /// CHECK: InstructionsSysvar account
+    #[account(address = sysvar::instructions::ID)]
+    instructions: UncheckedAccount<'info>,
+    associated_token_program: Program<'info, AssociatedToken>,
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> FinalizeBatch<'info> {
+    // Positions match_batch introspects, checked against the struct's account
+    // metas in tests. The matches follow the struct's accounts.
+    pub const SIGNER_INDEX: usize = 0;
+    pub const ACCOUNT_COUNT: usize = 15;
+    // match_donation_state, match_key, donor and match_record
+    pub const ACCOUNTS_PER_MATCH: usize = 4;
+
+    // The number of matches listed in the remaining accounts
+    pub fn match_count(remaining_accounts: usize) -> Result<usize> {
+        require!(
+            remaining_accounts > 0 && remaining_accounts % Self::ACCOUNTS_PER_MATCH == 0,
+            BonkPawsError::InvalidBatch
+        );
+        Ok(remaining_accounts / Self::ACCOUNTS_PER_MATCH)
+    }
+
+    pub fn finalize_batch(&mut self, remaining_accounts: &'info [AccountInfo<'info>], bumps: FinalizeBatchBumps) -> Result<()> {
+        let match_count = Self::match_count(remaining_accounts.len())?;
+
+        // Make sure we are preceded by a swap, preceded by a match batch
+        let ixs = self.instructions.to_account_info();
+        let current_index = load_current_index_checked(&ixs)? as usize;
+        require_gte!(current_index, 2, BonkPawsError::InvalidInstructionIndex);
+
+        let ix = load_instruction_at_checked(current_index.checked_sub(2).ok_or(BonkPawsError::Overflow)?, &ixs)
+            .map_err(|_| BonkPawsError::MissingDonateIx)?;
+        require_instruction_eq!(ix, crate::ID, crate::instruction::MatchBatch::DISCRIMINATOR, BonkPawsError::InvalidInstruction);
+
+        let swap_ix = load_instruction_at_checked(current_index.checked_sub(1).ok_or(BonkPawsError::Overflow)?, &ixs)
+            .map_err(|_| BonkPawsError::MissingDonateIx)?;
+        let swap = swap_venue(&swap_ix)?;
+        require!(!swap.is_exact_in(), BonkPawsError::InvalidRoute);
+
+        // Listing a state twice would pay its match twice
+        require_ascending_keys(remaining_accounts.iter().step_by(Self::ACCOUNTS_PER_MATCH).map(|account| account.key()))?;
+
+        let mut matches = Vec::with_capacity(match_count);
+        let mut target_lamports: u64 = 0;
+        for accounts in remaining_accounts.chunks(Self::ACCOUNTS_PER_MATCH) {
+            let [match_donation_state, match_key, donor, match_record] = accounts else {
+                return Err(BonkPawsError::InvalidBatch.into());
+            };
+
+            // Only a match that is mid swap can be finalized
+            let mut match_donation_state = Account::<MatchDonationState>::try_from(match_donation_state)?;
+            match_donation_state.transition(MatchStatus::Finalized)?;
+            require_keys_eq!(match_donation_state.match_key, match_key.key(), BonkPawsError::InvalidMatchKey);
+            require_keys_eq!(match_donation_state.donor, donor.key(), BonkPawsError::InvalidBatch);
+
+            target_lamports = target_lamports
+                .checked_add(match_donation_state.target_lamports)
+                .ok_or(BonkPawsError::Overflow)?;
+            matches.push((match_donation_state, match_key, donor, match_record));
+        }
+
+        // The swap's proceeds must cover every target, anything over is surplus
+        let received_lamports = self.signer_wsol.amount;
+        require_gte!(received_lamports, target_lamports, BonkPawsError::InvalidwSolBalance);
+        let surplus_lamports = received_lamports
+            .checked_sub(target_lamports)
+            .ok_or(BonkPawsError::Overflow)?;
+
+        // Every state in the batch snapshotted the same vault balance
+        let pre_match_vault_balance = matches[0].0.pre_match_vault_balance;
+        let targets: Vec<u64> = matches.iter().map(|(match_donation_state, _, _, _)| match_donation_state.target_lamports).collect();
+        let surplus_shares = surplus_shares(surplus_lamports, &targets)?;
+        let mint = self.mint.key();
+
+        let mut settlement = self.settlement();
+        settlement.validate(swap.as_ref())?;
+        settlement.unwrap_proceeds()?;
+        for (match_donation_state, match_key, _, _) in matches.iter() {
+            settlement.pay((*match_key).clone(), match_donation_state.target_lamports)?;
+        }
+
+        // Surplus under the charity policy is shared between the charities, otherwise it's sent on at once
+        match settlement.config.surplus_policy {
+            SurplusPolicy::Charity => {
+                for ((_, match_key, _, _), surplus_share) in matches.iter().zip(surplus_shares.iter()) {
+                    settlement.route_surplus(*surplus_share, (*match_key).clone())?;
+                }
+            },
+            _ => settlement.route_surplus(surplus_lamports, matches[0].1.clone())?,
+        }
+
+        let (amount_spent, amount_burned) = settlement.settle_vault(pre_match_vault_balance, bumps.donation_state, bumps.vault_mint_state)?;
+        settlement.record(target_lamports, target_lamports, match_count as u64)?;
+
+        /*
+
+            Record The Matches
+
+            The amount spent and burned is attributed to each match in
+            proportion to its target, rounding down, as is the surplus
+            priced into its execution. Each match donation state is closed,
+            refunding its rent to the donor.
+
+        */
+
+        let quoted_price = MatchRecord::price(swap.quoted_out_amount(), swap.quoted_in_amount())?;
+        for ((match_donation_state, _, donor, match_record), surplus_share) in matches.into_iter().zip(surplus_shares) {
+            let target = match_donation_state.target_lamports;
+            let record = MatchRecord::new(
+                &match_donation_state,
+                target,
+                target.checked_add(surplus_share).ok_or(BonkPawsError::Overflow)?,
+                mint,
+                pro_rata(amount_spent, target, target_lamports)?,
+                pro_rata(amount_burned, target, target_lamports)?,
+                quoted_price,
+            )?;
+            self.create_match_record(match_record, record)?;
+
+            match_donation_state.close(donor.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /*
+
+        Create A Match Record
+
+        Batched records are listed in the remaining accounts, so they are
+        created the way Anchor's init creates an account: with a single
+        create_account when the address holds no lamports, or by topping
+        up, allocating and assigning it otherwise, so lamports sent to the
+        address ahead of time can't block it. The record is then written
+        through a typed account, which sets its discriminator.
+
+    */
+    fn create_match_record(&self, match_record: &'info AccountInfo<'info>, record: MatchRecord) -> Result<()> {
+        let nonce = record.nonce.to_le_bytes();
+        let (key, bump) = Pubkey::find_program_address(&[b"match_record", nonce.as_ref()], &crate::ID);
+        require_keys_eq!(match_record.key(), key, BonkPawsError::InvalidBatch);
+
+        // Never overwrite an account we already own or one holding data
+        require_keys_eq!(*match_record.owner, System::id(), BonkPawsError::MatchRecordExists);
+        require!(match_record.data_is_empty(), BonkPawsError::MatchRecordExists);
+
+        let signer_seeds: &[&[&[u8]]] = &[&[b"match_record", nonce.as_ref(), &[bump]]];
+        let rent_exempt_lamports = Rent::get()?.minimum_balance(MatchRecord::INIT_SPACE);
+
+        if match_record.lamports() == 0 {
+            let create_accounts = CreateAccount {
+                from: self.signer.to_account_info(),
+                to: match_record.clone(),
+            };
+            let create_ctx = CpiContext::new_with_signer(self.system_program.to_account_info(), create_accounts, signer_seeds);
+
+            create_account(create_ctx, rent_exempt_lamports, MatchRecord::INIT_SPACE as u64, &crate::ID)?;
+        } else {
+            let rent_shortfall = rent_exempt_lamports.saturating_sub(match_record.lamports());
+            if rent_shortfall > 0 {
+                let transfer_accounts = Transfer {
+                    from: self.signer.to_account_info(),
+                    to: match_record.clone(),
+                };
+                let transfer_ctx = CpiContext::new(self.system_program.to_account_info(), transfer_accounts);
+
+                transfer(transfer_ctx, rent_shortfall)?;
+            }
+
+            let allocate_accounts = Allocate {
+                account_to_allocate: match_record.clone(),
+            };
+            let allocate_ctx = CpiContext::new_with_signer(self.system_program.to_account_info(), allocate_accounts, signer_seeds);
+
+            allocate(allocate_ctx, MatchRecord::INIT_SPACE as u64)?;
+
+            let assign_accounts = Assign {
+                account_to_assign: match_record.clone(),
+            };
+            let assign_ctx = CpiContext::new_with_signer(self.system_program.to_account_info(), assign_accounts, signer_seeds);
+
+            assign(assign_ctx, &crate::ID)?;
+        }
+
+        let mut match_record = Account::<MatchRecord>::try_from_unchecked(match_record)?;
+        match_record.set_inner(record);
+        match_record.exit(&crate::ID)
+    }
+
+    fn settlement(&mut self) -> Settlement<'_, 'info> {
+        Settlement {
+            signer: &self.signer,
+            treasury: self.treasury.as_ref(),
+            mint: &self.mint,
+            vault: &self.vault,
+            vault_mint_state: &mut self.vault_mint_state,
+            keeper_token: self.keeper_token.as_ref(),
+            signer_wsol: &self.signer_wsol,
+            wsol_vault: self.wsol_vault.as_ref(),
+            config: &self.config,
+            donation_state: &mut self.donation_state,
+            token_program: &self.token_program,
+            system_program: &self.system_program,
+        }
+    }
+}
+
+// Keys must strictly ascend, so that no account is listed twice
+pub fn require_ascending_keys(keys: impl IntoIterator<Item = Pubkey>) -> Result<()> {
+    let mut previous_key: Option<Pubkey> = None;
+    for key in keys {
+        if let Some(previous_key) = previous_key {
+            require!(key > previous_key, BonkPawsError::InvalidBatch);
+        }
+        previous_key = Some(key);
+    }
+
+    Ok(())
+}
+
+// A match's share of an amount in proportion to its target, rounding down
+pub fn pro_rata(amount: u64, target_lamports: u64, total_lamports: u64) -> Result<u64> {
+    let share = (amount as u128)
+        .checked_mul(target_lamports as u128).ok_or(BonkPawsError::Overflow)?
+        .checked_div(total_lamports as u128).ok_or(BonkPawsError::Overflow)?;
+
+    u64::try_from(share).map_err(|_| BonkPawsError::Overflow.into())
+}
+
+// Each match's share of the surplus, with the rounding remainder going to the first
+pub fn surplus_shares(surplus_lamports: u64, targets: &[u64]) -> Result<Vec<u64>> {
+    let total_lamports = targets
+        .iter()
+        .try_fold(0u64, |total, target| total.checked_add(*target))
+        .ok_or(BonkPawsError::Overflow)?;
+
+    let mut shares = targets
+        .iter()
+        .map(|target| pro_rata(surplus_lamports, *target, total_lamports))
+        .collect::<Result<Vec<u64>>>()?;
+
+    let remainder = surplus_lamports
+        .checked_sub(shares.iter().sum())
+        .ok_or(BonkPawsError::Overflow)?;
+    if let Some(first) = shares.first_mut() {
+        *first = first.checked_add(remainder).ok_or(BonkPawsError::Overflow)?;
+    }
+
+    Ok(shares)
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::solana_program::hash::hash;
+
+    use super::*;
+
+    fn key(name: &str) -> Pubkey {
+        Pubkey::new_from_array(hash(name.as_bytes()).to_bytes())
+    }
+
+    #[test]
+    fn account_indices_match_account_metas() {
+        // Lists every account, so that adding one fails to compile until this is updated
+        let metas = crate::accounts::FinalizeBatch {
+            signer: key("signer"),
+            treasury: Some(key("treasury")),
+            mint: key("mint"),
+            vault: key("vault"),
+            vault_mint_state: key("vault_mint_state"),
+            keeper_token: Some(key("keeper_token")),
+            wsol: key("wsol"),
+            signer_wsol: key("signer_wsol"),
+            wsol_vault: Some(key("wsol_vault")),
+            config: key("config"),
+            donation_state: key("donation_state"),
+            instructions: key("instructions"),
+            associated_token_program: key("associated_token_program"),
+            token_program: key("token_program"),
+            system_program: key("system_program"),
+        }
+        .to_account_metas(None);
+
+        assert_eq!(metas.len(), FinalizeBatch::ACCOUNT_COUNT);
+        assert_eq!(metas[FinalizeBatch::SIGNER_INDEX].pubkey, key("signer"));
+    }
+
+    #[test]
+    fn remaining_accounts_hold_whole_matches() {
+        assert_eq!(FinalizeBatch::match_count(4).unwrap(), 1);
+        assert_eq!(FinalizeBatch::match_count(12).unwrap(), 3);
+
+        for remaining_accounts in [0, 3, 5, 10] {
+            assert_eq!(FinalizeBatch::match_count(remaining_accounts).err(), Some(BonkPawsError::InvalidBatch.into()));
+        }
+    }
+
+    #[test]
+    fn keys_must_strictly_ascend() {
+        let mut keys: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        keys.sort();
+        assert!(require_ascending_keys(keys.clone()).is_ok());
+        assert!(require_ascending_keys(vec![]).is_ok());
+
+        // A state listed twice
+        assert_eq!(
+            require_ascending_keys(vec![keys[0], keys[1], keys[1]]).err(),
+            Some(BonkPawsError::InvalidBatch.into())
+        );
+        assert_eq!(
+            require_ascending_keys(keys.into_iter().rev()).err(),
+            Some(BonkPawsError::InvalidBatch.into())
+        );
+    }
+
+    #[test]
+    fn amounts_are_attributed_pro_rata() {
+        assert_eq!(pro_rata(1_000, 100, 600).unwrap(), 166);
+        assert_eq!(pro_rata(1_000, 500, 600).unwrap(), 833);
+        assert_eq!(pro_rata(u64::MAX, 1, 1).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn surplus_is_shared_pro_rata() {
+        // 1, 2 and 3 lamports, with the remaining lamport going to the first match
+        let shares = surplus_shares(7, &[100, 200, 300]).unwrap();
+        assert_eq!(shares, [2, 2, 3]);
+        assert_eq!(shares.iter().sum::<u64>(), 7);
+
+        assert_eq!(surplus_shares(0, &[100, 200]).unwrap(), [0, 0]);
+        assert_eq!(surplus_shares(5, &[100]).unwrap(), [5]);
+    }
+}
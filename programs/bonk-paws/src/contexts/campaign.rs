@@ -0,0 +1,131 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::{MAX_CAMPAIGN_CHARITIES, MAX_MATCH_RATIO_BPS},
+    errors::BonkPawsError,
+    state::{Campaign, Config},
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CampaignArgs {
+    pub sponsor: Pubkey,
+    pub start: i64,
+    pub end: i64,
+    pub budget_lamports: u64,
+    pub match_ratio_bps: u16,
+    pub charity_ids: Vec<u64>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct CreateCampaign<'info> {
+    #[account(mut)]
+    admin: Signer<'info>,
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"campaign", id.to_le_bytes().as_ref()],
+        bump,
+        space = Campaign::INIT_SPACE
+    )]
+    campaign: Account<'info, Campaign>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> CreateCampaign<'info> {
+    // Only one campaign may run at a time, until its sponsor closes it
+    pub fn create_campaign(&mut self, id: u64, args: CampaignArgs, bumps: CreateCampaignBumps) -> Result<()> {
+        require!(self.config.campaign.is_none(), BonkPawsError::CampaignInProgress);
+        require_gt!(args.end, args.start, BonkPawsError::InvalidCampaign);
+        require_gte!(MAX_CAMPAIGN_CHARITIES, args.charity_ids.len(), BonkPawsError::InvalidCampaign);
+        require_gte!(MAX_MATCH_RATIO_BPS, args.match_ratio_bps, BonkPawsError::InvalidMatchRatio);
+
+        self.campaign.set_inner(
+            Campaign {
+                id,
+                sponsor: args.sponsor,
+                start: args.start,
+                end: args.end,
+                budget_lamports: args.budget_lamports,
+                reserved_lamports: 0,
+                match_ratio_bps: args.match_ratio_bps,
+                charity_ids: args.charity_ids,
+                bump: bumps.campaign,
+            }
+        );
+        self.config.campaign = Some(self.campaign.key());
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct ReduceCampaignBudget<'info> {
+    sponsor: Signer<'info>,
+    #[account(
+        mut,
+        has_one = sponsor,
+        seeds = [b"campaign", campaign.id.to_le_bytes().as_ref()],
+        bump = campaign.bump,
+    )]
+    campaign: Account<'info, Campaign>,
+}
+
+impl<'info> ReduceCampaignBudget<'info> {
+    // The budget only caps what the vault matches and is never escrowed, so
+    // sponsors may lower it by any amount matches haven't reserved
+    pub fn reduce_campaign_budget(&mut self, lamports: u64) -> Result<()> {
+        self.campaign.reduce_budget(lamports)
+    }
+}
+
+#[derive(Accounts)]
+pub struct CloseCampaign<'info> {
+    sponsor: Signer<'info>,
+    #[account(mut)]
+    admin: SystemAccount<'info>,
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+    #[account(
+        mut,
+        close = admin,
+        has_one = sponsor,
+        seeds = [b"campaign", campaign.id.to_le_bytes().as_ref()],
+        bump = campaign.bump,
+    )]
+    campaign: Account<'info, Campaign>,
+}
+
+impl<'info> CloseCampaign<'info> {
+    /*
+
+        Close Campaign
+
+        Once a campaign is no longer running, its sponsor may close it and
+        return the rent to the admin who created it. Pending matches keep
+        their reservation in the vault, and releasing one skips a campaign
+        that has since been closed.
+
+    */
+    pub fn close_campaign(&mut self) -> Result<()> {
+        require!(!self.campaign.is_active(Clock::get()?.unix_timestamp), BonkPawsError::CampaignInProgress);
+
+        if self.config.campaign == Some(self.campaign.key()) {
+            self.config.campaign = None;
+        }
+
+        Ok(())
+    }
+}
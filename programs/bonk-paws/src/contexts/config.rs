@@ -0,0 +1,271 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{Transfer, transfer},
+    Discriminator,
+};
+
+use crate::{
+    constants::{bonk, BONK_BURN_BPS, MATCH_RATIO_DENOMINATOR, MAX_MATCH_RATIO_BPS, MAX_POOLS, MAX_VAULT_MINTS},
+    errors::BonkPawsError,
+    program::BonkPaws,
+    state::{ApprovalScheme, CharityConfig, Config, DonationState, KeeperPolicy, MatchCaps, OraclePolicy, RoutePolicy, SurplusPolicy, VaultMint},
+};
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    admin: Signer<'info>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"config"],
+        bump,
+        space = Config::INIT_SPACE
+    )]
+    config: Account<'info, Config>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    program: Program<'info, BonkPaws>,
+    // Only the upgrade authority may initialize the config
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()))]
+    program_data: Account<'info, ProgramData>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeConfig<'info> {
+    pub fn initialize_config(&mut self, approval_scheme: ApprovalScheme, bumps: InitializeConfigBumps) -> Result<()> {
+        self.config.set_inner(
+            Config {
+                admin: self.admin.key(),
+                approval_scheme,
+                merkle_root: [0u8; 32],
+                route_policy: RoutePolicy::default(),
+                oracle_policy: OraclePolicy::default(),
+                platform_fee_account: Pubkey::default(),
+                keeper_policy: KeeperPolicy::default(),
+                match_ratio_bps: MATCH_RATIO_DENOMINATOR as u16,
+                match_caps: MatchCaps::default(),
+                match_ttl_seconds: 0,
+                max_reserved_lamports: 0,
+                surplus_policy: SurplusPolicy::default(),
+                campaign: None,
+                vault_mints: vec![
+                    VaultMint {
+                        mint: bonk::ID,
+                        burn_bps: BONK_BURN_BPS,
+                        keeper_reward: 0,
+                        price_account: Pubkey::default(),
+                    }
+                ],
+                bump: bumps.config,
+            }
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    admin: Signer<'info>,
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    pub fn set_admin(&mut self, admin: Pubkey) -> Result<()> {
+        self.config.admin = admin;
+        Ok(())
+    }
+
+    pub fn set_approval_scheme(&mut self, approval_scheme: ApprovalScheme) -> Result<()> {
+        self.config.approval_scheme = approval_scheme;
+        Ok(())
+    }
+
+    // Setting the root to all zeroes disables the allowlist
+    pub fn set_merkle_root(&mut self, merkle_root: [u8; 32]) -> Result<()> {
+        self.config.merkle_root = merkle_root;
+        Ok(())
+    }
+
+    pub fn set_route_policy(&mut self, route_policy: RoutePolicy) -> Result<()> {
+        require_gte!(MAX_POOLS, route_policy.pools.len(), BonkPawsError::InvalidPool);
+        self.config.route_policy = route_policy;
+        Ok(())
+    }
+
+    pub fn set_oracle_policy(&mut self, oracle_policy: OraclePolicy) -> Result<()> {
+        if oracle_policy.is_enabled() {
+            require_gt!(oracle_policy.max_deviation_bps, 0, BonkPawsError::InvalidOraclePolicy);
+            require_gte!(10_000, oracle_policy.max_deviation_bps, BonkPawsError::InvalidOraclePolicy);
+            require_gt!(oracle_policy.max_staleness_slots, 0, BonkPawsError::InvalidOraclePolicy);
+        }
+        self.config.oracle_policy = oracle_policy;
+        Ok(())
+    }
+
+    pub fn set_platform_fee_account(&mut self, platform_fee_account: Pubkey) -> Result<()> {
+        self.config.platform_fee_account = platform_fee_account;
+        Ok(())
+    }
+
+    pub fn set_keeper_policy(&mut self, keeper_policy: KeeperPolicy) -> Result<()> {
+        self.config.keeper_policy = keeper_policy;
+        Ok(())
+    }
+
+    pub fn set_match_ratio(&mut self, match_ratio_bps: u16) -> Result<()> {
+        require_gte!(MAX_MATCH_RATIO_BPS, match_ratio_bps, BonkPawsError::InvalidMatchRatio);
+        self.config.match_ratio_bps = match_ratio_bps;
+        Ok(())
+    }
+
+    pub fn set_match_caps(&mut self, match_caps: MatchCaps) -> Result<()> {
+        if match_caps.donor_cap_lamports > 0 {
+            require_gt!(match_caps.donor_window_seconds, 0, BonkPawsError::InvalidMatchCaps);
+        }
+        self.config.match_caps = match_caps;
+        Ok(())
+    }
+
+    pub fn set_match_ttl(&mut self, match_ttl_seconds: i64) -> Result<()> {
+        self.config.match_ttl_seconds = match_ttl_seconds;
+        Ok(())
+    }
+
+    pub fn set_max_reserved_lamports(&mut self, max_reserved_lamports: u64) -> Result<()> {
+        self.config.max_reserved_lamports = max_reserved_lamports;
+        Ok(())
+    }
+
+    pub fn set_surplus_policy(&mut self, surplus_policy: SurplusPolicy) -> Result<()> {
+        self.config.surplus_policy = surplus_policy;
+        Ok(())
+    }
+
+    // Vault mints are listed in priority order, and each may only be listed once
+    pub fn set_vault_mints(&mut self, vault_mints: Vec<VaultMint>) -> Result<()> {
+        require!(!vault_mints.is_empty(), BonkPawsError::InvalidVaultMint);
+        require_gte!(MAX_VAULT_MINTS, vault_mints.len(), BonkPawsError::InvalidVaultMint);
+
+        for (i, vault_mint) in vault_mints.iter().enumerate() {
+            require_gte!(10000, vault_mint.burn_bps, BonkPawsError::InvalidVaultMint);
+            require!(
+                vault_mints[..i].iter().all(|listed| listed.mint.ne(&vault_mint.mint)),
+                BonkPawsError::InvalidVaultMint
+            );
+        }
+
+        self.config.vault_mints = vault_mints;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateCharityConfig<'info> {
+    #[account(mut)]
+    admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+    charity: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        seeds = [b"charity", charity.key().as_ref()],
+        bump,
+        space = CharityConfig::INIT_SPACE
+    )]
+    charity_config: Account<'info, CharityConfig>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> UpdateCharityConfig<'info> {
+    // Passing no ratio closes the charity config, reverting to the global match ratio
+    pub fn set_charity_match_ratio(&mut self, match_ratio_bps: Option<u16>, bumps: UpdateCharityConfigBumps) -> Result<()> {
+        match match_ratio_bps {
+            Some(match_ratio_bps) => {
+                require_gte!(MAX_MATCH_RATIO_BPS, match_ratio_bps, BonkPawsError::InvalidMatchRatio);
+                self.charity_config.set_inner(
+                    CharityConfig {
+                        charity: self.charity.key(),
+                        match_ratio_bps,
+                        bump: bumps.charity_config,
+                    }
+                )
+            }
+            None => self.charity_config.close(self.admin.to_account_info())?,
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct MigrateDonationState<'info> {
+    #[account(mut)]
+    admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"donation_state"],
+        bump,
+    )]
+    /// CHECK: DonationState in an earlier layout, which can't be deserialized until migrated
+    donation_state: UncheckedAccount<'info>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateDonationState<'info> {
+    /*
+
+        Migrate Donation State
+
+        The donation state has grown new fields since it was first created.
+        Growing the account zeroes the new fields, which is their initial
+        value, while the admin tops up the rent for the larger account.
+
+    */
+    pub fn migrate_donation_state(&mut self) -> Result<()> {
+        let donation_state = self.donation_state.to_account_info();
+        require_keys_eq!(*donation_state.owner, crate::ID, ErrorCode::AccountOwnedByWrongProgram);
+        require!(
+            donation_state.try_borrow_data()?.starts_with(&DonationState::DISCRIMINATOR),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+
+        if donation_state.data_len() >= DonationState::INIT_SPACE {
+            return Ok(());
+        }
+
+        let rent_shortfall = Rent::get()?
+            .minimum_balance(DonationState::INIT_SPACE)
+            .saturating_sub(donation_state.lamports());
+        if rent_shortfall > 0 {
+            let transfer_accounts = Transfer {
+                from: self.admin.to_account_info(),
+                to: donation_state.clone(),
+            };
+            let transfer_cpi = CpiContext::new(self.system_program.to_account_info(), transfer_accounts);
+
+            transfer(transfer_cpi, rent_shortfall)?;
+        }
+
+        donation_state.realloc(DonationState::INIT_SPACE, true)?;
+
+        Ok(())
+    }
+}
@@ -10,10 +10,14 @@ use anchor_lang::{
     system_program::{Transfer, transfer},
 };
 
+use anchor_spl::token::{spl_token::native_mint, Mint, TokenAccount};
+
 use crate::{
+    approval::{AllowlistProof, Approval},
     constants::*,
     errors::BonkPawsError,
-    state::{DonationState, MatchDonationState, DonationHistory}
+    programs::pyth::PriceAccount,
+    state::{ApprovalScheme, Campaign, CharityConfig, CharityMatchCap, Config, DonationState, DonorMatchCap, MatchDonationState, MatchStatus, DonationHistory, SolTreasury}
 };
 
 #[derive(Accounts)]
@@ -23,6 +27,23 @@ pub struct DonateSol<'info> {
     donor: Signer<'info>,
     #[account(mut)]
     charity: SystemAccount<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+    #[account(
+        seeds = [b"charity", charity.key().as_ref()],
+        bump,
+    )]
+    /// CHECK: CharityConfig, which may not be initialized
+    charity_config: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"campaign", campaign.id.to_le_bytes().as_ref()],
+        bump = campaign.bump,
+    )]
+    campaign: Option<Account<'info, Campaign>>,
     #[account(
         init_if_needed,
         payer = donor,
@@ -31,6 +52,23 @@ pub struct DonateSol<'info> {
         space = DonationState::INIT_SPACE
     )]
     donation_state: Account<'info, DonationState>,
+    #[account(
+        address = config.priority_mint()?.mint @ BonkPawsError::InvalidVaultMint
+    )]
+    mint: Account<'info, Mint>,
+    #[account(
+        associated_token::mint = mint,
+        associated_token::authority = donation_state,
+    )]
+    vault: Option<Account<'info, TokenAccount>>,
+    #[account(address = config.priority_mint()?.price_account)]
+    /// CHECK: Pyth style price account of the mint in SOL, parsed in the handler
+    oracle: Option<UncheckedAccount<'info>>,
+    #[account(
+        seeds = [b"sol_treasury"],
+        bump = sol_treasury.bump,
+    )]
+    sol_treasury: Option<Account<'info, SolTreasury>>,
     #[account(
         init,
         payer = donor,
@@ -39,6 +77,22 @@ pub struct DonateSol<'info> {
         space = MatchDonationState::INIT_SPACE
     )]
     match_donation_state: Option<Account<'info, MatchDonationState>>,
+    #[account(
+        init_if_needed,
+        payer = donor,
+        seeds = [b"donor_cap", donor.key().as_ref()],
+        bump,
+        space = DonorMatchCap::INIT_SPACE
+    )]
+    donor_match_cap: Option<Account<'info, DonorMatchCap>>,
+    #[account(
+        init_if_needed,
+        payer = donor,
+        seeds = [b"charity_cap", charity.key().as_ref()],
+        bump,
+        space = CharityMatchCap::INIT_SPACE
+    )]
+    charity_match_cap: Option<Account<'info, CharityMatchCap>>,
     #[account(
         init, 
         payer = donor,
@@ -54,14 +108,9 @@ pub struct DonateSol<'info> {
 }
 
 impl<'info> DonateSol<'info> {        
-    pub fn donate_sol(&mut self, seed: u64, sol_donation: u64) -> Result<()> {
-        
-        // We check that the MatchDonation State is initialized only when the threshold is met
-        if sol_donation < MIN_MATCH_THRESHOLD {
-            require!(self.match_donation_state.is_none(), BonkPawsError::NotMatchingDonation);
-        } else {
-            require!(self.match_donation_state.is_some(), BonkPawsError::NotMatchingDonation);
-        }
+    // MIN_MATCH_THRESHOLD is tunable and may be zero
+    #[allow(clippy::absurd_extreme_comparisons)]
+    pub fn donate_sol(&mut self, seed: u64, sol_donation: u64, allowlist_proof: Option<AllowlistProof>) -> Result<()> {
 
         // Send the SOL to the charity address
         let transfer_accounts = Transfer {
@@ -84,60 +133,107 @@ impl<'info> DonateSol<'info> {
 
         let ixs = self.instructions.to_account_info();
         let current_index = load_current_index_checked(&ixs)? as usize;
-        require_gte!(current_index, 1, BonkPawsError::InvalidInstructionIndex);
         let current_ix = load_instruction_at_checked(current_index, &ixs)?;
         require!(crate::check_id(&current_ix.program_id), BonkPawsError::ProgramMismatch);
 
         /*
         
-            Make sure previous IX is an ed25519 signature verifying the donation address
+            Make sure the donation address is approved
+
+            Either by a Merkle proof against the configured allowlist, or by
+            the previous IX being a signature verifying the donation address.
+            Depending on the configured approval scheme, this is either an
+            ed25519 or a secp256r1 precompile instruction.
 
         */
         
-        // Check program ID is instructions sysvar
-        let signature_ix = load_instruction_at_checked(current_index.checked_sub(1).ok_or(BonkPawsError::Overflow)?, &ixs)?;
-        require_keys_eq!(ed25519program::ID, signature_ix.program_id, BonkPawsError::ProgramMismatch);  
-
-        // Ensure a strict instruction header format: 
-        require!([0x01, 0x00, 0x30, 0x00, 0xff, 0xff, 0x10, 0x00, 0xff, 0xff, 0x70, 0x00, 0x48, 0x00, 0xff, 0xff].eq(&signature_ix.data[0..16]), BonkPawsError::SignatureHeaderMismatch);
-
-        // Ensure signing authority is correct
-        require!(signing_authority::ID.to_bytes().eq(&signature_ix.data[16..48]), BonkPawsError::SignatureAuthorityMismatch);
-
-        // The following fetches the id for usage in the transaction history
-        let mut charity_id_data: [u8;8] = [0u8;8]; 
-        charity_id_data.copy_from_slice(&signature_ix.data[0x70..0x78]);
-        let id = u64::from_le_bytes(charity_id_data);
-
-        // The following fetches the charity key for later varification
-        let mut donation_key_data: [u8;32] = [0u8;32]; 
-        donation_key_data.copy_from_slice(&signature_ix.data[0x78..0x98]);
-        let donation_key = Pubkey::from(donation_key_data);
+        let Approval { id, charity, match_key } = match allowlist_proof {
+            Some(allowlist_proof) => Approval::try_from_allowlist_proof(allowlist_proof, self.charity.key(), &self.config.merkle_root)?,
+            None => {
+                require_gte!(current_index, 1, BonkPawsError::InvalidInstructionIndex);
+                let signature_ix = load_instruction_at_checked(current_index.checked_sub(1).ok_or(BonkPawsError::Overflow)?, &ixs)?;
+                match self.config.approval_scheme {
+                    ApprovalScheme::Ed25519 => Approval::try_from_ed25519_ix(&signature_ix)?,
+                    ApprovalScheme::Secp256r1 { authority } => Approval::try_from_secp256r1_ix(&signature_ix, &authority)?,
+                }
+            }
+        };
 
         // Ensure that the Transfer is going to the charity address
-        require_keys_eq!(self.charity.key(), donation_key, BonkPawsError::InvalidCharityAddress);
+        require_keys_eq!(self.charity.key(), charity, BonkPawsError::InvalidCharityAddress);
 
-        // The following fetches the charity key for later verification
-        let mut match_key_data: [u8;32] = [0u8;32]; 
-        match_key_data.copy_from_slice(&signature_ix.data[0x98..0xB8]);
-        let match_key = Pubkey::from(match_key_data);
+        // A running campaign's match ratio takes precedence over the charity's and the global one
+        let timestamp = Clock::get()?.unix_timestamp;
+        let campaign_match_ratio_bps = self.campaign_match_ratio_bps(id, timestamp)?;
+        let match_ratio_bps = match campaign_match_ratio_bps {
+            Some(match_ratio_bps) => match_ratio_bps,
+            None => self.match_ratio_bps()?,
+        };
+        let target_lamports = (sol_donation as u128)
+            .checked_mul(match_ratio_bps as u128).ok_or(BonkPawsError::Overflow)?
+            .checked_div(MATCH_RATIO_DENOMINATOR as u128).ok_or(BonkPawsError::Overflow)?;
+        let target_lamports = u64::try_from(target_lamports).map_err(|_| BonkPawsError::Overflow)?;
+
+        // We check that the MatchDonation State is initialized only when the threshold is met
+        // and there is something to match, as a match ratio of zero disables matching
+        let matching = sol_donation >= MIN_MATCH_THRESHOLD && target_lamports > 0;
+        require!(self.match_donation_state.is_some() == matching, BonkPawsError::NotMatchingDonation);
 
         // Ensure that we're not making any mistake:
         if match_key == Pubkey::default() {
-            require!(self.match_donation_state.is_none(), BonkPawsError::InvalidMatchKey);
-            require!(sol_donation < MIN_MATCH_THRESHOLD, BonkPawsError::InvalidMatchKey);
+            require!(!matching, BonkPawsError::InvalidMatchKey);
         }
 
         // If we have to match later we need to create the MatchDonation State
-        if self.match_donation_state.is_some() {
-            self.match_donation_state.as_mut().unwrap().set_inner(           
-                MatchDonationState {
-                    id,
-                    donation_amount: sol_donation,
-                    match_key,
-                    seed,
-                }
-            );
+        if matching {
+            // Enforce the per donor and per charity match caps
+            let match_caps = self.config.match_caps;
+            if match_caps.donor_cap_lamports > 0 {
+                self.donor_match_cap
+                    .as_mut()
+                    .ok_or(BonkPawsError::MissingMatchCap)?
+                    .record(match_caps.donor_cap_lamports, match_caps.donor_window_seconds, target_lamports, timestamp)?;
+            }
+            if match_caps.charity_daily_cap_lamports > 0 {
+                self.charity_match_cap
+                    .as_mut()
+                    .ok_or(BonkPawsError::MissingMatchCap)?
+                    .record(match_caps.charity_daily_cap_lamports, target_lamports, timestamp)?;
+            }
+
+            // Reserve the target against the vault's unreserved capacity
+            self.reserve(target_lamports)?;
+
+            // Reserve the target in the campaign's budget
+            let campaign = match (campaign_match_ratio_bps, self.campaign.as_mut()) {
+                (Some(_), Some(campaign)) => {
+                    campaign.reserve(id, target_lamports)?;
+                    Some(campaign.key())
+                },
+                _ => None,
+            };
+
+            let nonce = self.donation_state.match_nonce;
+            self.donation_state.match_nonce = nonce.checked_add(1).ok_or(BonkPawsError::Overflow)?;
+
+            if let Some(match_donation_state) = self.match_donation_state.as_mut() {
+                match_donation_state.set_inner(           
+                    MatchDonationState {
+                        id,
+                        donation_amount: sol_donation,
+                        target_lamports,
+                        match_key,
+                        seed,
+                        nonce,
+                        campaign,
+                        donor: self.donor.key(),
+                        charity,
+                        created_at: timestamp,
+                        pre_match_vault_balance: 0,
+                        status: MatchStatus::Pending,
+                    }
+                );
+            }
         }
         
         // Increment the amount of SOL donated by donors
@@ -149,10 +245,89 @@ impl<'info> DonateSol<'info> {
                 donor: self.donor.key(),
                 id,
                 donation_amount: sol_donation,
-                timestamp: Clock::get()?.unix_timestamp
+                timestamp,
             }
         );
 
         Ok(())
     }
-}
+
+    /*
+
+        Vault Solvency
+
+        Pending matches reserve lamports on the donation state until they
+        are finalized or expire. When the oracle is enabled, we value the
+        vault of the priority mint at the oracle price and only create a
+        match while its unreserved capacity covers the target. Matches may
+        swap from any vault mint, but only the priority mint's vault is
+        valued, so the other vaults are headroom the estimate leaves out.
+        As a match may be paid from either source, the SOL treasury's
+        balance counts towards capacity.
+
+        The estimate is conservative: each pending match may pay a keeper
+        reward from the vault, swap at the worst slippage we allow and burn
+        its share on top of the amount spent. Without an oracle we can't
+        value the vault at all, so reservations are capped by the admin,
+        or left unbounded until a cap is set.
+
+    */
+    fn reserve(&mut self, target_lamports: u64) -> Result<()> {
+        let pending_matches = self.donation_state.pending_matches.checked_add(1).ok_or(BonkPawsError::Overflow)?;
+
+        let capacity = match self.config.oracle_policy.is_enabled() {
+            true => Some(self.vault_capacity(pending_matches)?),
+            false => (self.config.max_reserved_lamports > 0).then_some(self.config.max_reserved_lamports as u128),
+        };
+        let capacity = match (capacity, self.sol_treasury.as_ref()) {
+            (Some(capacity), Some(sol_treasury)) => Some(
+                capacity
+                    .checked_add(SolTreasury::available_lamports(&sol_treasury.to_account_info())? as u128)
+                    .ok_or(BonkPawsError::Overflow)?
+            ),
+            (capacity, _) => capacity,
+        };
+
+        self.donation_state.reserve(target_lamports, capacity)
+    }
+
+    // The vault's value in lamports at the oracle price, less the headroom pending matches need
+    fn vault_capacity(&self, pending_matches: u64) -> Result<u128> {
+        let vault_mint = self.config.priority_mint()?;
+        let vault = self.vault.as_ref().ok_or(BonkPawsError::InvalidVaultAccount)?;
+        let oracle = self.oracle.as_ref().ok_or(BonkPawsError::InvalidOracle)?;
+        let price_account = PriceAccount::try_from(&oracle.to_account_info())?;
+
+        let keeper_rewards = vault_mint.keeper_reward.checked_mul(pending_matches).ok_or(BonkPawsError::Overflow)?;
+        self.config.oracle_policy
+            .value(&price_account, vault.amount.saturating_sub(keeper_rewards), self.mint.decimals, native_mint::DECIMALS)?
+            .checked_mul(10000).ok_or(BonkPawsError::Overflow)?
+            .checked_div(10000 + MAX_SLIPPAGE_BPS as u128).ok_or(BonkPawsError::Overflow)?
+            .checked_mul(10000).ok_or(BonkPawsError::Overflow)?
+            .checked_div(10000 + vault_mint.burn_bps as u128).ok_or(BonkPawsError::Overflow.into())
+    }
+
+    // While a campaign is set donors must pass it, though its match ratio only
+    // applies to eligible charities while it's running
+    fn campaign_match_ratio_bps(&self, charity_id: u64, timestamp: i64) -> Result<Option<u16>> {
+        match self.config.campaign {
+            Some(key) => {
+                let campaign = self.campaign.as_ref().ok_or(BonkPawsError::InvalidCampaign)?;
+                require_keys_eq!(campaign.key(), key, BonkPawsError::InvalidCampaign);
+                Ok((campaign.is_active(timestamp) && campaign.is_eligible(charity_id)).then_some(campaign.match_ratio_bps))
+            },
+            None => {
+                require!(self.campaign.is_none(), BonkPawsError::InvalidCampaign);
+                Ok(None)
+            },
+        }
+    }
+
+    // The charity's match ratio if it has one, otherwise the global one
+    fn match_ratio_bps(&self) -> Result<u16> {
+        match self.charity_config.owner.eq(&crate::ID) {
+            true => Ok(CharityConfig::try_deserialize(&mut &self.charity_config.try_borrow_data()?[..])?.match_ratio_bps),
+            false => Ok(self.config.match_ratio_bps),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,122 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::BonkPawsError,
+    state::{Config, DonationState, MatchDonationState},
+};
+
+#[derive(Accounts)]
+pub struct ExpireMatch<'info> {
+    #[account(mut)]
+    donor: SystemAccount<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"donation_state"],
+        bump,
+    )]
+    donation_state: Account<'info, DonationState>,
+    #[account(
+        mut,
+        close = donor,
+        has_one = donor,
+        seeds = [b"match_donation", match_donation_state.seed.to_le_bytes().as_ref()],
+        bump,
+    )]
+    match_donation_state: Account<'info, MatchDonationState>,
+    #[account(mut)]
+    /// CHECK: The match's campaign, which may have been closed, checked in release
+    campaign: Option<UncheckedAccount<'info>>,
+    #[account(
+        mut,
+        seeds = [b"donor_cap", donor.key().as_ref()],
+        bump,
+    )]
+    /// CHECK: DonorMatchCap, which may not be initialized
+    donor_match_cap: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"charity_cap", match_donation_state.charity.as_ref()],
+        bump,
+    )]
+    /// CHECK: CharityMatchCap, which may not be initialized
+    charity_match_cap: UncheckedAccount<'info>,
+}
+
+impl<'info> ExpireMatch<'info> {
+    // Anyone may close a match that has outlived the configured TTL
+    pub fn expire_match(&mut self) -> Result<()> {
+        require_gt!(self.config.match_ttl_seconds, 0, BonkPawsError::MatchNotExpired);
+
+        let expires_at = self.match_donation_state.created_at
+            .checked_add(self.config.match_ttl_seconds)
+            .ok_or(BonkPawsError::Overflow)?;
+        require_gte!(Clock::get()?.unix_timestamp, expires_at, BonkPawsError::MatchNotExpired);
+
+        self.match_donation_state.release(
+            &mut self.donation_state,
+            self.campaign.as_deref(),
+            &self.donor_match_cap,
+            &self.charity_match_cap,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct CancelMatch<'info> {
+    admin: Signer<'info>,
+    #[account(mut)]
+    donor: SystemAccount<'info>,
+    #[account(
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"donation_state"],
+        bump,
+    )]
+    donation_state: Account<'info, DonationState>,
+    #[account(
+        mut,
+        close = donor,
+        has_one = donor,
+        seeds = [b"match_donation", match_donation_state.seed.to_le_bytes().as_ref()],
+        bump,
+    )]
+    match_donation_state: Account<'info, MatchDonationState>,
+    #[account(mut)]
+    /// CHECK: The match's campaign, which may have been closed, checked in release
+    campaign: Option<UncheckedAccount<'info>>,
+    #[account(
+        mut,
+        seeds = [b"donor_cap", donor.key().as_ref()],
+        bump,
+    )]
+    /// CHECK: DonorMatchCap, which may not be initialized
+    donor_match_cap: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"charity_cap", match_donation_state.charity.as_ref()],
+        bump,
+    )]
+    /// CHECK: CharityMatchCap, which may not be initialized
+    charity_match_cap: UncheckedAccount<'info>,
+}
+
+impl<'info> CancelMatch<'info> {
+    pub fn cancel_match(&mut self) -> Result<()> {
+        self.match_donation_state.release(
+            &mut self.donation_state,
+            self.campaign.as_deref(),
+            &self.donor_match_cap,
+            &self.charity_match_cap,
+        )
+    }
+}
@@ -11,45 +11,55 @@ use anchor_lang::{
 use anchor_spl::{
     associated_token::AssociatedToken,
     token::{
-        burn, close_account, transfer as spl_transfer, Burn, CloseAccount, Mint, Token,
-        TokenAccount, Transfer as SplTransfer,
+        burn, close_account, revoke, sync_native, transfer as spl_transfer, Burn, CloseAccount,
+        Mint, Revoke, SyncNative, Token, TokenAccount, Transfer as SplTransfer,
     },
 };
 
 use crate::{
-    constants::{bonk, signing_authority, wsol},
+    constants::{bonk, wsol},
     errors::BonkPawsError,
-    programs::jupiter::{self, SharedAccountsExactOutRoute},
-    require_discriminator_eq, require_instruction_eq,
-    state::{DonationState, MatchDonationState},
+    programs::{swap_venue, SwapVenue},
+    require_instruction_eq,
+    state::{Config, DonationState, MatchDonationState, MatchRecord, MatchStatus, SurplusPolicy, VaultMintState},
 };
 
 #[derive(Accounts)]
 pub struct FinalizeDonation<'info> {
-    #[account(
-        mut,
-        address = signing_authority::ID
-    )]
+    #[account(mut)]
     signer: Signer<'info>,
     #[account(mut)]
     match_key: SystemAccount<'info>,
+    // The donor paid the match state's rent, so it is refunded to them
+    #[account(mut)]
+    donor: SystemAccount<'info>,
+    #[account(mut)]
+    treasury: Option<SystemAccount<'info>>,
     #[account(
         mut,
-        address = bonk::ID
+        constraint = config.vault_mint(&mint.key()).is_ok() @ BonkPawsError::InvalidVaultMint
     )]
-    bonk: Account<'info, Mint>,
+    mint: Account<'info, Mint>,
     #[account(
         mut,
-        associated_token::mint = bonk,
-        associated_token::authority = signer,
+        associated_token::mint = mint,
+        associated_token::authority = donation_state,
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        seeds = [b"vault_mint", mint.key().as_ref()],
+        bump,
+        space = VaultMintState::INIT_SPACE
     )]
-    signer_bonk: Account<'info, TokenAccount>,
+    vault_mint_state: Account<'info, VaultMintState>,
     #[account(
         mut,
-        associated_token::mint = bonk,
-        associated_token::authority = donation_state,
+        token::mint = mint,
+        token::authority = signer,
     )]
-    bonk_vault: Account<'info, TokenAccount>,
+    keeper_token: Option<Account<'info, TokenAccount>>,
     #[account(
         address = wsol::ID
     )]
@@ -60,6 +70,17 @@ pub struct FinalizeDonation<'info> {
         associated_token::authority = signer,
     )]
     signer_wsol: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = wsol,
+        associated_token::authority = donation_state,
+    )]
+    wsol_vault: Option<Account<'info, TokenAccount>>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
     #[account(
         mut,
         seeds = [b"donation_state"],
@@ -68,12 +89,21 @@ pub struct FinalizeDonation<'info> {
     donation_state: Account<'info, DonationState>,
     #[account(
         mut,
-        close = signer,
+        close = donor,
         has_one = match_key,
+        has_one = donor,
         seeds = [b"match_donation", match_donation_state.seed.to_le_bytes().as_ref()],
         bump,
     )]
     match_donation_state: Account<'info, MatchDonationState>,
+    #[account(
+        init,
+        payer = signer,
+        seeds = [b"match_record", match_donation_state.nonce.to_le_bytes().as_ref()],
+        bump,
+        space = MatchRecord::INIT_SPACE
+    )]
+    match_record: Account<'info, MatchRecord>,
     /// CHECK: InstructionsSysvar account
     #[account(address = sysvar::instructions::ID)]
     instructions: UncheckedAccount<'info>,
@@ -83,7 +113,15 @@ pub struct FinalizeDonation<'info> {
 }
 
 impl<'info> FinalizeDonation<'info> {
+    // Positions of the accounts match_donation introspects, checked against
+    // the struct's account metas in tests
+    pub const SIGNER_INDEX: usize = 0;
+    pub const MATCH_DONATION_STATE_INDEX: usize = 13;
+
     pub fn finalize_donation(&mut self, bumps: FinalizeDonationBumps) -> Result<()> {
+        // Only a match that is mid swap can be finalized
+        self.match_donation_state.transition(MatchStatus::Finalized)?;
+
         /*
         
             Match Donate Instruction
@@ -92,7 +130,7 @@ impl<'info> FinalizeDonation<'info> {
             that this instruction:
 
             - Won't work with CPI
-            - Is directly preceded by a Jupiter Swap instruction
+            - Is directly preceded by a swap instruction on a supported venue
             - Which is directly preceded by a Donate instruction
             - Contains the correct match Donation amount
         
@@ -128,30 +166,129 @@ impl<'info> FinalizeDonation<'info> {
                 .ok_or(BonkPawsError::Overflow)?,
             &ixs,
         ).map_err(|_| BonkPawsError::MissingDonateIx)?;
-        require_instruction_eq!(
-            swap_ix,
-            jupiter::ID,
-            SharedAccountsExactOutRoute::DISCRIMINATOR,
-            BonkPawsError::InvalidInstruction
-        );
-        let swap_ix_data = SharedAccountsExactOutRoute::try_from_slice(&swap_ix.data[8..])?;
+        let swap = swap_venue(&swap_ix)?;
+
+        /*
+
+            Verify wSOL Proceeds
 
-        let donation_amount = swap_ix_data.out_amount;
+            Match ensures the signer's wSOL account starts out empty, so its
+            balance is exactly what the swap returned. It must cover the 
+            target, otherwise the signer's own SOL would be covering a short
+            swap.
+
+            The charity is matched exactly the target. Whatever the swap
+            returned over it, be it an ExactIn route beating its quote or
+            dust over an ExactOut target, is surplus the surplus policy
+            handles. Unspent tokens never leave the vault, as the swap spends
+            from it directly as a delegate.
+
+        */
+
+        let matched_lamports = self.match_donation_state.target_lamports;
+        let received_lamports = self.signer_wsol.amount;
+        require_gte!(received_lamports, matched_lamports, BonkPawsError::InvalidwSolBalance);
+
+        let surplus_lamports = received_lamports
+            .checked_sub(matched_lamports)
+            .ok_or(BonkPawsError::Overflow)?;
+
+        let pre_match_vault_balance = self.match_donation_state.pre_match_vault_balance;
+        let match_key = self.match_key.to_account_info();
+
+        let mut settlement = self.settlement();
+        settlement.validate(swap.as_ref())?;
+        settlement.unwrap_proceeds()?;
 
         /*
         
-            Close wSOL Account
+            Donate Native SOL To Charity Account
+
+        */
+
+        settlement.pay(match_key.clone(), matched_lamports)?;
+        settlement.route_surplus(surplus_lamports, match_key)?;
+
+        let (amount_spent, amount_burned) = settlement.settle_vault(pre_match_vault_balance, bumps.donation_state, bumps.vault_mint_state)?;
+        settlement.record(matched_lamports, matched_lamports, 1)?;
+
+        /*
+
+            Record The Match
 
-            To avoid adding an intermediate vault account and an additional
-            transfer instruction, we will simply close the wSOL ATA, sending 
-            its entire lamports balance to the donor, followed by refunding
-            the non-rent-exempt lamports to the charity account. To do this,
-            we must first save the "token" balance of the wSOL account which
-            should be slightly lower than the total lamports balance. 
+            The match donation state is closed, so we keep a permanent
+            record of the finalized match along with its execution quality.
 
         */
 
-        // Close wSOL account and send to the signer
+        let quoted_price = MatchRecord::price(swap.quoted_out_amount(), swap.quoted_in_amount())?;
+        let match_record = MatchRecord::new(
+            &self.match_donation_state,
+            matched_lamports,
+            received_lamports,
+            self.mint.key(),
+            amount_spent,
+            amount_burned,
+            quoted_price,
+        )?;
+        self.match_record.set_inner(match_record);
+
+        Ok(())
+    }
+
+    fn settlement(&mut self) -> Settlement<'_, 'info> {
+        Settlement {
+            signer: &self.signer,
+            treasury: self.treasury.as_ref(),
+            mint: &self.mint,
+            vault: &self.vault,
+            vault_mint_state: &mut self.vault_mint_state,
+            keeper_token: self.keeper_token.as_ref(),
+            signer_wsol: &self.signer_wsol,
+            wsol_vault: self.wsol_vault.as_ref(),
+            config: &self.config,
+            donation_state: &mut self.donation_state,
+            token_program: &self.token_program,
+            system_program: &self.system_program,
+        }
+    }
+}
+
+// The accounts a swap's proceeds are settled with, shared by single and batched matches
+pub struct Settlement<'a, 'info> {
+    pub signer: &'a Signer<'info>,
+    pub treasury: Option<&'a SystemAccount<'info>>,
+    pub mint: &'a Account<'info, Mint>,
+    pub vault: &'a Account<'info, TokenAccount>,
+    pub vault_mint_state: &'a mut Account<'info, VaultMintState>,
+    pub keeper_token: Option<&'a Account<'info, TokenAccount>>,
+    pub signer_wsol: &'a Account<'info, TokenAccount>,
+    pub wsol_vault: Option<&'a Account<'info, TokenAccount>>,
+    pub config: &'a Account<'info, Config>,
+    pub donation_state: &'a mut Account<'info, DonationState>,
+    pub token_program: &'a Program<'info, Token>,
+    pub system_program: &'a Program<'info, System>,
+}
+
+impl<'a, 'info> Settlement<'a, 'info> {
+    // The swap must have spent from the vault we settle
+    pub fn validate(&self, swap: &dyn SwapVenue) -> Result<()> {
+        require_keys_eq!(swap.source_mint(), self.mint.key(), BonkPawsError::InvalidVaultMint);
+        require_keys_eq!(swap.source_token_account(), self.vault.key(), BonkPawsError::InvalidVaultATA);
+        Ok(())
+    }
+
+    /*
+    
+        Close wSOL Account
+
+        To avoid adding an intermediate vault account and an additional
+        transfer instruction, we will simply close the wSOL ATA, sending 
+        its entire lamports balance to the signer, followed by paying out
+        the matched lamports to the charity account. 
+
+    */
+    pub fn unwrap_proceeds(&self) -> Result<()> {
         let close_wsol_accounts = CloseAccount {
             account: self.signer_wsol.to_account_info(),
             destination: self.signer.to_account_info(),
@@ -160,78 +297,199 @@ impl<'info> FinalizeDonation<'info> {
         let close_wsol_ctx =
             CpiContext::new(self.token_program.to_account_info(), close_wsol_accounts);
 
-        close_account(close_wsol_ctx)?;
-
-        /*
-        
-            Donate Native SOL To Charity Account
-
-        */
+        close_account(close_wsol_ctx)
+    }
 
+    pub fn pay(&self, to: AccountInfo<'info>, lamports: u64) -> Result<()> {
         let transfer_accounts = Transfer {
             from: self.signer.to_account_info(),
-            to: self.match_key.to_account_info(),
+            to,
         };
 
         let transfer_ctx =
             CpiContext::new(self.system_program.to_account_info(), transfer_accounts);
 
-        transfer(transfer_ctx, donation_amount)?;
+        transfer(transfer_ctx, lamports)
+    }
 
-        // Transfer surplus bonk from the signer back to the vault
-        let transfer_accounts = SplTransfer {
-            from: self.signer_bonk.to_account_info(),
-            to: self.bonk_vault.to_account_info(),
-            authority: self.signer.to_account_info(),
+    /*
+
+        Handle Surplus SOL
+
+        Depending on the surplus policy, surplus is donated to the charity,
+        wrapped into the vault's wSOL account, or sent to our treasury.
+
+    */
+    pub fn route_surplus(&mut self, surplus_lamports: u64, charity: AccountInfo<'info>) -> Result<()> {
+        if surplus_lamports == 0 {
+            return Ok(());
+        }
+
+        let surplus_destination = match self.config.surplus_policy {
+            SurplusPolicy::Charity => charity,
+            SurplusPolicy::Vault => self.wsol_vault.ok_or(BonkPawsError::InvalidSurplusDestination)?.to_account_info(),
+            SurplusPolicy::Treasury { treasury } => {
+                let treasury_account = self.treasury.ok_or(BonkPawsError::InvalidSurplusDestination)?;
+                require_keys_eq!(treasury_account.key(), treasury, BonkPawsError::InvalidSurplusDestination);
+                treasury_account.to_account_info()
+            },
         };
-        let transfer_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
 
-        spl_transfer(transfer_ctx, self.signer_bonk.amount)?;
+        self.pay(surplus_destination.clone(), surplus_lamports)?;
 
-        self.bonk_vault.reload();
+        // Wrapped SOL must be synced to reflect the lamports it received
+        if self.config.surplus_policy == SurplusPolicy::Vault {
+            let sync_accounts = SyncNative {
+                account: surplus_destination,
+            };
+            let sync_ctx = CpiContext::new(self.token_program.to_account_info(), sync_accounts);
 
-        // Calculate how much BONK was spent to match
-        let bonk_matched_amount: u64 = self
-            .match_donation_state
-            .donation_amount
-            .checked_sub(self.bonk_vault.amount)
-            .ok_or(BonkPawsError::Overflow)?;
-        msg!("Bonk matched: {}", bonk_matched_amount);
-        // Calculate burn amount
-        let bonk_burn_amount: u64 = bonk_matched_amount
-            .checked_div(100)
+            sync_native(sync_ctx)?;
+        }
+
+        self.donation_state.sol_surplus = self
+            .donation_state
+            .sol_surplus
+            .checked_add(surplus_lamports)
             .ok_or(BonkPawsError::Overflow)?;
-        msg!("Bonk burned: {}", bonk_burn_amount);
 
-        // Burn 1% of the bonk donated
-        let seeds = &[b"donation_state".as_ref(), &[bumps.donation_state]];
+        Ok(())
+    }
+
+    // Revoke the signer's delegation, burn per the mint's rule and reward the keeper
+    pub fn settle_vault(&mut self, pre_match_vault_balance: u64, donation_state_bump: u8, vault_mint_state_bump: u8) -> Result<(u64, u64)> {
+        let vault_mint = *self.config.vault_mint(&self.mint.key())?;
+
+        let seeds = &[b"donation_state".as_ref(), &[donation_state_bump]];
         let signer_seeds = &[&seeds[..]];
 
-        let burn_accounts = Burn {
-            mint: self.bonk.to_account_info(),
-            from: self.bonk_vault.to_account_info(),
+        let revoke_accounts = Revoke {
+            source: self.vault.to_account_info(),
             authority: self.donation_state.to_account_info(),
         };
-        let burn_ctx = CpiContext::new_with_signer(
+        let revoke_ctx = CpiContext::new_with_signer(
             self.token_program.to_account_info(),
-            burn_accounts,
+            revoke_accounts,
             signer_seeds,
         );
 
-        burn(burn_ctx, bonk_burn_amount)?;
+        revoke(revoke_ctx)?;
+
+        // Calculate how much was spent from the vault to match
+        let amount_spent: u64 = pre_match_vault_balance
+            .checked_sub(self.vault.amount)
+            .ok_or(BonkPawsError::Overflow)?;
+        // Calculate burn amount
+        let amount_burned = (amount_spent as u128)
+            .checked_mul(vault_mint.burn_bps as u128)
+            .ok_or(BonkPawsError::Overflow)?
+            .checked_div(10000)
+            .ok_or(BonkPawsError::Overflow)?;
+        let amount_burned = u64::try_from(amount_burned).map_err(|_| BonkPawsError::Overflow)?;
+
+        // Burn a share of the amount spent, for mints with a burn rule
+        if amount_burned > 0 {
+            let burn_accounts = Burn {
+                mint: self.mint.to_account_info(),
+                from: self.vault.to_account_info(),
+                authority: self.donation_state.to_account_info(),
+            };
+            let burn_ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                burn_accounts,
+                signer_seeds,
+            );
+
+            burn(burn_ctx, amount_burned)?;
+        }
+
+        // Reward the keeper from the vault
+        if vault_mint.keeper_reward > 0 {
+            let keeper_token = self.keeper_token.ok_or(BonkPawsError::InvalidVaultAccount)?;
+
+            let transfer_accounts = SplTransfer {
+                from: self.vault.to_account_info(),
+                to: keeper_token.to_account_info(),
+                authority: self.donation_state.to_account_info(),
+            };
+            let transfer_ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            );
+
+            spl_transfer(transfer_ctx, vault_mint.keeper_reward)?;
+        }
+
+        self.vault_mint_state.mint = self.mint.key();
+        self.vault_mint_state.bump = vault_mint_state_bump;
+        self.vault_mint_state.amount_burned = self
+            .vault_mint_state
+            .amount_burned
+            .checked_add(amount_burned)
+            .ok_or(BonkPawsError::Overflow)?;
+
+        if self.mint.key() == bonk::ID {
+            self.donation_state.bonk_burned = self
+                .donation_state
+                .bonk_burned
+                .checked_add(amount_burned)
+                .ok_or(BonkPawsError::Overflow)?;
+        }
+
+        Ok((amount_spent, amount_burned))
+    }
 
-        // Update the donation state
+    // Update the donation state
+    pub fn record(&mut self, matched_lamports: u64, target_lamports: u64, matches: u64) -> Result<()> {
         self.donation_state.sol_matched = self
             .donation_state
             .sol_matched
-            .checked_add(donation_amount)
-            .ok_or(BonkPawsError::Overflow)?;
-        self.donation_state.bonk_burned = self
-            .donation_state
-            .bonk_burned
-            .checked_add(bonk_burn_amount)
+            .checked_add(matched_lamports)
             .ok_or(BonkPawsError::Overflow)?;
+        self.donation_state.release(target_lamports, matches);
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::solana_program::hash::hash;
+
+    use super::*;
+
+    fn key(name: &str) -> Pubkey {
+        Pubkey::new_from_array(hash(name.as_bytes()).to_bytes())
+    }
+
+    #[test]
+    fn account_indices_match_account_metas() {
+        // Lists every account, so that adding one fails to compile until this is updated
+        let metas = crate::accounts::FinalizeDonation {
+            signer: key("signer"),
+            match_key: key("match_key"),
+            donor: key("donor"),
+            treasury: Some(key("treasury")),
+            mint: key("mint"),
+            vault: key("vault"),
+            vault_mint_state: key("vault_mint_state"),
+            keeper_token: Some(key("keeper_token")),
+            wsol: key("wsol"),
+            signer_wsol: key("signer_wsol"),
+            wsol_vault: Some(key("wsol_vault")),
+            config: key("config"),
+            donation_state: key("donation_state"),
+            match_donation_state: key("match_donation_state"),
+            match_record: key("match_record"),
+            instructions: key("instructions"),
+            associated_token_program: key("associated_token_program"),
+            token_program: key("token_program"),
+            system_program: key("system_program"),
+        }
+        .to_account_metas(None);
+
+        assert_eq!(metas[FinalizeDonation::SIGNER_INDEX].pubkey, key("signer"));
+        assert_eq!(metas[FinalizeDonation::MATCH_DONATION_STATE_INDEX].pubkey, key("match_donation_state"));
+    }
+}
@@ -12,32 +12,23 @@ use anchor_lang::{
 
 use anchor_spl::{
     token::{
-        transfer as spl_transfer, Mint, Token, TokenAccount, Transfer as SplTransfer
+        approve, Approve, Mint, Token, TokenAccount
     }, 
     associated_token::AssociatedToken
 };
 
 use crate::{
-    constants::{bonk, signing_authority, wsol}, errors::BonkPawsError, programs::jupiter::{
-        self, SharedAccountsExactOutRoute, SharedAccountsExactOutRouteAccountMetas
-    }, require_discriminator_eq, require_instruction_eq, state::{DonationState, MatchDonationState}
+    contexts::FinalizeDonation, constants::{signing_authority, wsol, MAX_SLIPPAGE_BPS}, errors::BonkPawsError, programs::{pyth::PriceAccount, swap_venue, SwapVenue}, require_instruction_eq, state::{Config, DonationState, MatchDonationState, MatchStatus}
 };
 
 #[derive(Accounts)]
 pub struct MatchDonation<'info> {
-    #[account(mut, address = signing_authority::ID)]
+    #[account(mut)]
     signer: Signer<'info>,
     #[account(
-        address = bonk::ID
+        constraint = config.vault_mint(&mint.key()).is_ok() @ BonkPawsError::InvalidVaultMint
     )]
-    bonk: Account<'info, Mint>,
-    #[account(
-        init_if_needed,
-        payer = signer,
-        associated_token::mint = bonk,
-        associated_token::authority = signer,
-    )]
-    signer_bonk: Account<'info, TokenAccount>,
+    mint: Account<'info, Mint>,
     #[account(
         address = wsol::ID
     )]
@@ -49,6 +40,11 @@ pub struct MatchDonation<'info> {
         associated_token::authority = signer,
     )]
     signer_wsol: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
     #[account(
         seeds = [b"donation_state"],
         bump,    
@@ -62,10 +58,13 @@ pub struct MatchDonation<'info> {
     match_donation_state: Account<'info, MatchDonationState>,
     #[account(
         mut,
-        associated_token::mint = bonk,
+        associated_token::mint = mint,
         associated_token::authority = donation_state,
     )]
-    bonk_vault: Account<'info, TokenAccount>,
+    vault: Account<'info, TokenAccount>,
+    #[account(address = config.vault_mint(&mint.key())?.price_account)]
+    /// CHECK: Pyth style price account of the mint in SOL, parsed in the handler
+    oracle: Option<UncheckedAccount<'info>>,
     #[account(address = sysvar::instructions::ID)]
     /// CHECK: InstructionsSysvar account
     instructions: UncheckedAccount<'info>,
@@ -100,95 +99,193 @@ impl<'info> MatchDonation<'info> {
         let current_ix = load_instruction_at_checked(current_index, &ixs)?;
         require!(crate::check_id(&current_ix.program_id), BonkPawsError::ProgramMismatch);
 
+        let swap_ix = load_instruction_at_checked(current_index + 1, &ixs).map_err(|_| BonkPawsError::MissingSwapIx)?;
+        let swap = swap_venue(&swap_ix)?;
+        self.swap_accounts().validate(swap.as_ref())?;
+
+        /*
+
+            Match Target
+
+            An ExactOut route must buy exactly the target amount, while an 
+            ExactIn route sells an exact amount from the vault and must return at 
+            least the target amount after slippage. The target amount is the
+            donation scaled by the match ratio.
+
+        */
+        match swap.is_exact_in() {
+            true => require_gte!(swap.min_out_amount()?, self.match_donation_state.target_lamports, BonkPawsError::InvalidSolanaAmount),
+            false => require_eq!(swap.min_out_amount()?, self.match_donation_state.target_lamports, BonkPawsError::InvalidSolanaAmount),
+        }
+
+        // Save the vault balance in MatchState PDA for cost comparison in finalization
+        self.match_donation_state.pre_match_vault_balance = self.vault.amount;
+        self.match_donation_state.transition(MatchStatus::Swapping)?;
+
+        self.swap_accounts().approve(swap.max_in_amount()?, bumps.donation_state)?;
+
+        /* 
+        
+            Match Finalize Instruction
+
+        */
+
+        if let Ok(ix) = load_instruction_at_checked(current_index.checked_add(2).ok_or(BonkPawsError::Overflow)?, &ixs) {
+            // Instruction checks
+            require_instruction_eq!(ix, crate::ID, crate::instruction::FinalizeDonation::DISCRIMINATOR, BonkPawsError::InvalidInstruction);
+            // Make sure the same keeper finalizes the match
+            require_keys_eq!(
+                ix.accounts.get(FinalizeDonation::SIGNER_INDEX).ok_or(BonkPawsError::UnauthorizedKeeper)?.pubkey,
+                self.signer.key(),
+                BonkPawsError::UnauthorizedKeeper
+            );
+            // Make sure match donation state key matches
+            require_keys_eq!(
+                ix.accounts.get(FinalizeDonation::MATCH_DONATION_STATE_INDEX).ok_or(BonkPawsError::InvalidMatchKey)?.pubkey, 
+                self.match_donation_state.key(), 
+                BonkPawsError::InvalidMatchKey
+            );
+        } else {
+            return Err(BonkPawsError::MissingFinalizeIx.into());
+        }
+        
+        Ok(())
+    }
+
+    fn swap_accounts(&self) -> SwapAccounts<'_, 'info> {
+        SwapAccounts {
+            signer: &self.signer,
+            mint: &self.mint,
+            wsol: &self.wsol,
+            signer_wsol: &self.signer_wsol,
+            config: &self.config,
+            donation_state: &self.donation_state,
+            vault: &self.vault,
+            oracle: self.oracle.as_ref(),
+            token_program: &self.token_program,
+        }
+    }
+}
+
+// The accounts a swap is checked against, shared by single and batched matches
+pub struct SwapAccounts<'a, 'info> {
+    pub signer: &'a Signer<'info>,
+    pub mint: &'a Account<'info, Mint>,
+    pub wsol: &'a Account<'info, Mint>,
+    pub signer_wsol: &'a Account<'info, TokenAccount>,
+    pub config: &'a Account<'info, Config>,
+    pub donation_state: &'a Account<'info, DonationState>,
+    pub vault: &'a Account<'info, TokenAccount>,
+    pub oracle: Option<&'a UncheckedAccount<'info>>,
+    pub token_program: &'a Program<'info, Token>,
+}
+
+impl<'a, 'info> SwapAccounts<'a, 'info> {
+    pub fn validate(&self, swap: &dyn SwapVenue) -> Result<()> {
+
+        /*
+
+            Keeper Checks
+
+            Matching is open to any keeper when enabled in the config. As a
+            third party keeper chooses the quote, we only allow them to match
+            while the oracle price check is enabled.
+
+        */
+        if self.signer.key() != signing_authority::ID {
+            require!(self.config.keeper_policy.permissionless, BonkPawsError::UnauthorizedKeeper);
+            require!(self.config.oracle_policy.is_enabled(), BonkPawsError::UnauthorizedKeeper);
+        }
+
         /* 
         
-            Match Jupiter Swap Instruction
+            Match Swap Instruction
             
-            Ensure that the next instruction after this one is a swap in the
-            Jupiter program. Checks include:
+            Ensure that the next instruction after this one is a swap on a
+            supported venue, which is Jupiter or our constant product pool.
+            Checks include:
 
             - Program ID and IX discriminator
+            - Transfer authority and token program matching
+            - Venue specific checks, for Jupiter these are its authorities,
+              no platform fees unless configured, and the AMM allowlist and
+              max hops protection, for the pool these are the pool allowlist
+              and a required oracle check
             - Token account matching
             - Mint account matching
-            - Deposit amount matching
-            - Minimum SOL amount matching
             - Max slippage protection
+            - Optional oracle price deviation protection
+
+            ExactOut swaps and ExactIn swaps are supported. The amount the
+            swap must buy is checked by the caller.
 
             By matching token accounts against our account struct which already 
             enforces mint constraints, we should be able to deduce the mint
             accounts in the instruction also match. Alas, we check them anyway
             just to be extra safe.
 
-            Basically, the only way this rugs is if Jupiter gets hacked.
+            Basically, the only way this rugs is if the venue gets hacked.
 
         */
-        let swap_ix = load_instruction_at_checked(current_index + 1, &ixs).map_err(|_| BonkPawsError::MissingSwapIx)?;
 
-        // Discriminator check
-        require_instruction_eq!(swap_ix, jupiter::ID, SharedAccountsExactOutRoute::DISCRIMINATOR, BonkPawsError::InvalidInstruction);
+        // Slippage and venue checks
+        require_gte!(MAX_SLIPPAGE_BPS, swap.slippage_bps(), BonkPawsError::InvalidSlippage);
+        swap.validate(self.config)?;
 
-        // Instruction data checks
-        let swap_ix_data = SharedAccountsExactOutRoute::try_from_slice(&swap_ix.data[8..])?;
-        require_gte!(50, swap_ix_data.slippage_bps, BonkPawsError::InvalidSlippage);
-        require_eq!(swap_ix_data.out_amount, self.match_donation_state.donation_amount, BonkPawsError::InvalidSolanaAmount);
+        // Oracle checks
+        if self.config.oracle_policy.is_enabled() {
+            let oracle = self.oracle.ok_or(BonkPawsError::InvalidOracle)?;
+            let price_account = PriceAccount::try_from(&oracle.to_account_info())?;
+            self.config.oracle_policy.validate(
+                &price_account,
+                swap.quoted_in_amount(),
+                self.mint.decimals,
+                swap.quoted_out_amount(),
+                self.wsol.decimals,
+            )?;
+        }
 
-        // Account checks
-        let swap_ix_accounts = SharedAccountsExactOutRouteAccountMetas::try_from(&swap_ix.accounts)?;
+        // Swap authority and program checks
+        require_keys_eq!(swap.user_transfer_authority(), self.signer.key(), BonkPawsError::InvalidTransferAuthority);
+        require_keys_eq!(swap.token_program(), self.token_program.key(), BonkPawsError::InvalidTokenProgram);
 
-        // BONK account checks
-        require_keys_eq!(swap_ix_accounts.source_mint.pubkey, self.bonk.key(), BonkPawsError::InvalidBonkMint);
-        require_keys_eq!(swap_ix_accounts.source_token_account.pubkey, self.signer_bonk.key(), BonkPawsError::InvalidBonkATA);
+        // Vault account checks, matches may swap from any of the config's vault mints
+        require_keys_eq!(swap.source_mint(), self.mint.key(), BonkPawsError::InvalidVaultMint);
+        require_keys_eq!(swap.source_token_account(), self.vault.key(), BonkPawsError::InvalidVaultATA);
 
         // wSOL account checks
-        require_keys_eq!(swap_ix_accounts.destination_mint.pubkey, self.wsol.key(), BonkPawsError::InvalidwSolMint);
-        require_keys_eq!(swap_ix_accounts.destination_token_account.pubkey, self.signer_wsol.key(), BonkPawsError::InvalidwSolATA);
+        require_keys_eq!(swap.destination_mint(), self.wsol.key(), BonkPawsError::InvalidwSolMint);
+        require_keys_eq!(swap.destination_token_account(), self.signer_wsol.key(), BonkPawsError::InvalidwSolATA);
+        // Start empty, so that finalize can verify the swap's proceeds
+        require_eq!(self.signer_wsol.amount, 0, BonkPawsError::InvalidwSolBalance);
 
-        // Save sum of BONK vault plus user BONK ATA balance in MatchState PDA for cost comparison in finalization
-        self.match_donation_state.donation_amount = self.bonk_vault.amount.checked_add(self.signer_bonk.amount).ok_or(BonkPawsError::Overflow)?;
-        //  Send maximum donation amount after slippage to user's ATA
-        let max_donation_amount: u64 = swap_ix_data.quoted_in_amount.checked_mul(10050).ok_or(BonkPawsError::Overflow)?.checked_div(10000).ok_or(BonkPawsError::Overflow)?;
+        Ok(())
+    }
 
-        /* 
+    /* 
 
-            Transfer the maximum amount of Bonk needed for the Signer to match the donation
-        
-        */
+        Delegate the maximum amount of the vault mint needed for the Signer to match the donation
 
+        Rather than staging tokens in the signer's own token account, the
+        swap spends directly from the vault as a delegate. The approval
+        is scoped to the maximum amount the swap can spend, and revoked 
+        again by the finalize instruction.
+    
+    */
+    pub fn approve(&self, max_donation_amount: u64, donation_state_bump: u8) -> Result<()> {
         let seeds = &[
             b"donation_state".as_ref(),
-            &[bumps.donation_state],
+            &[donation_state_bump],
         ];
         let signer_seeds = &[&seeds[..]];
 
-        // Transfer the exact amount needed to match the donation
-        let transfer_accounts = SplTransfer {
-            from: self.bonk_vault.to_account_info(),
-            to: self.signer_bonk.to_account_info(),
+        let approve_accounts = Approve {
+            to: self.vault.to_account_info(),
+            delegate: self.signer.to_account_info(),
             authority: self.donation_state.to_account_info(),
         };
-        let transfer_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_accounts, signer_seeds);
+        let approve_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), approve_accounts, signer_seeds);
 
-        spl_transfer(transfer_ctx, max_donation_amount)?;
-
-        /* 
-        
-            Match Finalize Instruction
-
-        */
-
-        if let Ok(ix) = load_instruction_at_checked(current_index.checked_add(2).ok_or(BonkPawsError::Overflow)?, &ixs) {
-            // Instruction checks
-            require_instruction_eq!(ix, crate::ID, crate::instruction::FinalizeDonation::DISCRIMINATOR, BonkPawsError::InvalidInstruction);
-            // Make sure match donation state key matches
-            require_keys_eq!(
-                ix.accounts.get(8).ok_or(BonkPawsError::InvalidMatchKey)?.pubkey, 
-                self.match_donation_state.key(), 
-                BonkPawsError::InvalidMatchKey
-            );
-        } else {
-            return Err(BonkPawsError::MissingFinalizeIx.into());
-        }
-        
-        Ok(())
+        approve(approve_ctx, max_donation_amount)
     }
-}
\ No newline at end of file
+}
@@ -1,3 +1,9 @@
+pub mod config;
+pub use config::*;
+
+pub mod campaign;
+pub use campaign::*;
+
 pub mod donate;
 pub use donate::*;
 
@@ -5,4 +11,13 @@ pub mod match_donation;
 pub use match_donation::*;
 
 pub mod finalize;
-pub use finalize::*;
\ No newline at end of file
+pub use finalize::*;
+
+pub mod batch;
+pub use batch::*;
+
+pub mod treasury;
+pub use treasury::*;
+
+pub mod expire;
+pub use expire::*;
\ No newline at end of file
@@ -0,0 +1,195 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+
+use crate::{
+    constants::signing_authority,
+    errors::BonkPawsError,
+    state::{Config, DonationState, MatchDonationState, MatchRecord, MatchSource, MatchStatus, SolTreasury},
+};
+
+#[derive(Accounts)]
+pub struct FundSolTreasury<'info> {
+    #[account(mut)]
+    sponsor: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = sponsor,
+        seeds = [b"sol_treasury"],
+        bump,
+        space = SolTreasury::INIT_SPACE
+    )]
+    sol_treasury: Account<'info, SolTreasury>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> FundSolTreasury<'info> {
+    pub fn fund_sol_treasury(&mut self, lamports: u64, bumps: FundSolTreasuryBumps) -> Result<()> {
+        require_gt!(lamports, 0, BonkPawsError::InvalidAmount);
+
+        let transfer_accounts = Transfer {
+            from: self.sponsor.to_account_info(),
+            to: self.sol_treasury.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(self.system_program.to_account_info(), transfer_accounts);
+
+        transfer(transfer_ctx, lamports)?;
+
+        self.sol_treasury.funded_lamports = self.sol_treasury.funded_lamports.checked_add(lamports).ok_or(BonkPawsError::Overflow)?;
+        self.sol_treasury.bump = bumps.sol_treasury;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSolTreasury<'info> {
+    admin: Signer<'info>,
+    #[account(mut)]
+    destination: SystemAccount<'info>,
+    #[account(
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"sol_treasury"],
+        bump = sol_treasury.bump,
+    )]
+    sol_treasury: Account<'info, SolTreasury>,
+}
+
+impl<'info> WithdrawSolTreasury<'info> {
+    // The admin may withdraw lamports the treasury holds above its rent exempt minimum
+    pub fn withdraw_sol_treasury(&mut self, lamports: u64) -> Result<()> {
+        require_gt!(lamports, 0, BonkPawsError::InvalidAmount);
+        require_gte!(
+            SolTreasury::available_lamports(&self.sol_treasury.to_account_info())?,
+            lamports,
+            BonkPawsError::InsufficientTreasuryBalance
+        );
+
+        // The treasury is owned by our program, so we can move its lamports directly
+        self.sol_treasury.sub_lamports(lamports)?;
+        self.destination.add_lamports(lamports)?;
+
+        self.sol_treasury.withdrawn_lamports = self.sol_treasury.withdrawn_lamports.checked_add(lamports).ok_or(BonkPawsError::Overflow)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct MatchFromTreasury<'info> {
+    #[account(mut)]
+    signer: Signer<'info>,
+    #[account(mut)]
+    match_key: SystemAccount<'info>,
+    // The donor paid the match state's rent, so it is refunded to them
+    #[account(mut)]
+    donor: SystemAccount<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"donation_state"],
+        bump,
+    )]
+    donation_state: Account<'info, DonationState>,
+    #[account(
+        mut,
+        seeds = [b"sol_treasury"],
+        bump = sol_treasury.bump,
+    )]
+    sol_treasury: Account<'info, SolTreasury>,
+    #[account(
+        mut,
+        close = donor,
+        has_one = match_key,
+        has_one = donor,
+        seeds = [b"match_donation", match_donation_state.seed.to_le_bytes().as_ref()],
+        bump,
+    )]
+    match_donation_state: Account<'info, MatchDonationState>,
+    #[account(
+        init,
+        payer = signer,
+        seeds = [b"match_record", match_donation_state.nonce.to_le_bytes().as_ref()],
+        bump,
+        space = MatchRecord::INIT_SPACE
+    )]
+    match_record: Account<'info, MatchRecord>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> MatchFromTreasury<'info> {
+    /*
+
+        Match From Treasury
+
+        Pays a pending match's target straight from the SOL treasury. The
+        target rather than the donation is paid, so the match ratio and the
+        reservation made at donation time hold for either source. With
+        no swap there is no quote for a keeper to choose, so any keeper may
+        match when matching is permissionless. A campaign's matches may be
+        paid from either the treasury or the token vault, as its budget is
+        reserved when the donation is made.
+
+    */
+    pub fn match_from_treasury(&mut self) -> Result<()> {
+        if self.signer.key() != signing_authority::ID {
+            require!(self.config.keeper_policy.permissionless, BonkPawsError::UnauthorizedKeeper);
+        }
+
+        // No swap is needed, so the match passes straight through swapping
+        self.match_donation_state.transition(MatchStatus::Swapping)?;
+        self.match_donation_state.transition(MatchStatus::Finalized)?;
+
+        let target_lamports = self.match_donation_state.target_lamports;
+        require_gte!(
+            SolTreasury::available_lamports(&self.sol_treasury.to_account_info())?,
+            target_lamports,
+            BonkPawsError::InsufficientTreasuryBalance
+        );
+
+        // The treasury is owned by our program, so we can move its lamports directly
+        self.sol_treasury.sub_lamports(target_lamports)?;
+        self.match_key.add_lamports(target_lamports)?;
+
+        self.sol_treasury.matched_lamports = self.sol_treasury.matched_lamports.checked_add(target_lamports).ok_or(BonkPawsError::Overflow)?;
+        self.donation_state.sol_matched = self.donation_state.sol_matched.checked_add(target_lamports).ok_or(BonkPawsError::Overflow)?;
+        self.donation_state.release(target_lamports, 1);
+
+        let match_donation_state = &self.match_donation_state;
+        self.match_record.set_inner(
+            MatchRecord {
+                id: match_donation_state.id,
+                seed: match_donation_state.seed,
+                nonce: match_donation_state.nonce,
+                donor: match_donation_state.donor,
+                match_key: match_donation_state.match_key,
+                campaign: match_donation_state.campaign,
+                donation_amount: match_donation_state.donation_amount,
+                lamports_matched: target_lamports,
+                // Nothing is spent from a vault, so there is no execution to price
+                source: MatchSource::Treasury,
+                mint: Pubkey::default(),
+                amount_spent: 0,
+                amount_burned: 0,
+                effective_price: 0,
+                quoted_price: 0,
+                slippage_bps: 0,
+                status: match_donation_state.status,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+}
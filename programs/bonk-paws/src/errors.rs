@@ -18,6 +18,8 @@ pub enum BonkPawsError {
     InvalidInstruction,
     #[msg("Invalid number of routes")]
     InvalidRoute,
+    #[msg("Route uses an AMM that is not allowed")]
+    AmmNotAllowed,
     #[msg("Invalid slippage")]
     InvalidSlippage,
     #[msg("Invalid Solana amount")]
@@ -28,6 +30,12 @@ pub enum BonkPawsError {
     InvalidBonkAccount,
     #[msg("Invalid BONK ATA")]
     InvalidBonkATA,
+    #[msg("Invalid vault account")]
+    InvalidVaultAccount,
+    #[msg("Invalid vault ATA")]
+    InvalidVaultATA,
+    #[msg("Mint is not allowed in the vault")]
+    InvalidVaultMint,
     #[msg("Invalid wSOL mint address")]
     InvalidwSolMint,
     #[msg("Invalid wSOL ATA")]
@@ -36,6 +44,8 @@ pub enum BonkPawsError {
     InvalidwSolAccount,
     #[msg("Invalid wSOL balance")]
     InvalidwSolBalance,
+    #[msg("Invalid surplus destination")]
+    InvalidSurplusDestination,
     #[msg("Invalid charity address")]
     InvalidCharityAddress,
     #[msg("Invalid charity Id")]
@@ -48,6 +58,70 @@ pub enum BonkPawsError {
     SignatureHeaderMismatch,
     #[msg("Signature authority mismatch")]
     SignatureAuthorityMismatch,
+    #[msg("Allowlist is disabled")]
+    AllowlistDisabled,
+    #[msg("Invalid Merkle proof")]
+    InvalidMerkleProof,
+
+    #[msg("Keeper is not authorized to match")]
+    UnauthorizedKeeper,
+    #[msg("Invalid transfer authority")]
+    InvalidTransferAuthority,
+    #[msg("Invalid token program")]
+    InvalidTokenProgram,
+    #[msg("Invalid platform fee")]
+    InvalidPlatformFee,
+    #[msg("Invalid Jupiter authority")]
+    InvalidJupiterAuthority,
+    #[msg("Invalid pool")]
+    InvalidPool,
+    #[msg("Pool is not allowed")]
+    PoolNotAllowed,
+    #[msg("Invalid oracle account")]
+    InvalidOracle,
+    #[msg("Oracle price is stale")]
+    StaleOracle,
+    #[msg("Quote deviates too far from the oracle price")]
+    OraclePriceDeviation,
+    #[msg("Invalid oracle policy")]
+    InvalidOraclePolicy,
+
+    #[msg("Match ratio exceeds the maximum")]
+    InvalidMatchRatio,
+
+    #[msg("Campaign is not active")]
+    CampaignInactive,
+    #[msg("Charity is not eligible for this campaign")]
+    CharityNotEligible,
+    #[msg("Campaign budget exceeded")]
+    CampaignBudgetExceeded,
+    #[msg("Invalid campaign")]
+    InvalidCampaign,
+    #[msg("Campaign is in progress")]
+    CampaignInProgress,
+
+    #[msg("Donor match cap exceeded")]
+    DonorMatchCapExceeded,
+    #[msg("Charity daily match cap exceeded")]
+    CharityMatchCapExceeded,
+    #[msg("Match cap account not found")]
+    MissingMatchCap,
+    #[msg("Invalid match caps")]
+    InvalidMatchCaps,
+
+    #[msg("Vault cannot cover the match")]
+    InsufficientVaultCapacity,
+    #[msg("SOL treasury cannot cover the match")]
+    InsufficientTreasuryBalance,
+
+    #[msg("Invalid match status")]
+    InvalidMatchStatus,
+    #[msg("Match has not expired")]
+    MatchNotExpired,
+    #[msg("Invalid match batch")]
+    InvalidBatch,
+    #[msg("Match record already exists")]
+    MatchRecordExists,
 
     #[msg("Not enough SOL Donated to Match")]
     NotMatchingDonation,
@@ -1,5 +1,6 @@
 use anchor_lang::prelude::*;
 
+pub mod approval;
 pub mod contexts;
 pub mod programs;
 pub mod errors;
@@ -8,6 +9,8 @@ pub mod constants;
 pub mod state;
 
 use contexts::*;
+use approval::AllowlistProof;
+use state::{ApprovalScheme, KeeperPolicy, MatchCaps, OraclePolicy, RoutePolicy, SurplusPolicy, VaultMint};
 
 declare_id!("bfpP4enQQ7ajSLaMWhAy6wYZYmRV6uxVid3r5hphh68");
 
@@ -15,8 +18,84 @@ declare_id!("bfpP4enQQ7ajSLaMWhAy6wYZYmRV6uxVid3r5hphh68");
 pub mod bonk_paws {
     use super::*;
 
-    pub fn donate(ctx: Context<DonateSol>, seeds: u64, sol_donation: u64) -> Result<()> {
-        ctx.accounts.donate_sol(seeds, sol_donation)
+    pub fn initialize_config(ctx: Context<InitializeConfig>, approval_scheme: ApprovalScheme) -> Result<()> {
+        ctx.accounts.initialize_config(approval_scheme, ctx.bumps)
+    }
+
+    pub fn set_admin(ctx: Context<UpdateConfig>, admin: Pubkey) -> Result<()> {
+        ctx.accounts.set_admin(admin)
+    }
+
+    pub fn set_approval_scheme(ctx: Context<UpdateConfig>, approval_scheme: ApprovalScheme) -> Result<()> {
+        ctx.accounts.set_approval_scheme(approval_scheme)
+    }
+
+    pub fn set_merkle_root(ctx: Context<UpdateConfig>, merkle_root: [u8; 32]) -> Result<()> {
+        ctx.accounts.set_merkle_root(merkle_root)
+    }
+
+    pub fn set_route_policy(ctx: Context<UpdateConfig>, route_policy: RoutePolicy) -> Result<()> {
+        ctx.accounts.set_route_policy(route_policy)
+    }
+
+    pub fn set_oracle_policy(ctx: Context<UpdateConfig>, oracle_policy: OraclePolicy) -> Result<()> {
+        ctx.accounts.set_oracle_policy(oracle_policy)
+    }
+
+    pub fn set_platform_fee_account(ctx: Context<UpdateConfig>, platform_fee_account: Pubkey) -> Result<()> {
+        ctx.accounts.set_platform_fee_account(platform_fee_account)
+    }
+
+    pub fn set_keeper_policy(ctx: Context<UpdateConfig>, keeper_policy: KeeperPolicy) -> Result<()> {
+        ctx.accounts.set_keeper_policy(keeper_policy)
+    }
+
+    pub fn set_match_ratio(ctx: Context<UpdateConfig>, match_ratio_bps: u16) -> Result<()> {
+        ctx.accounts.set_match_ratio(match_ratio_bps)
+    }
+
+    pub fn set_match_caps(ctx: Context<UpdateConfig>, match_caps: MatchCaps) -> Result<()> {
+        ctx.accounts.set_match_caps(match_caps)
+    }
+
+    pub fn set_match_ttl(ctx: Context<UpdateConfig>, match_ttl_seconds: i64) -> Result<()> {
+        ctx.accounts.set_match_ttl(match_ttl_seconds)
+    }
+
+    pub fn set_max_reserved_lamports(ctx: Context<UpdateConfig>, max_reserved_lamports: u64) -> Result<()> {
+        ctx.accounts.set_max_reserved_lamports(max_reserved_lamports)
+    }
+
+    pub fn set_surplus_policy(ctx: Context<UpdateConfig>, surplus_policy: SurplusPolicy) -> Result<()> {
+        ctx.accounts.set_surplus_policy(surplus_policy)
+    }
+
+    pub fn set_vault_mints(ctx: Context<UpdateConfig>, vault_mints: Vec<VaultMint>) -> Result<()> {
+        ctx.accounts.set_vault_mints(vault_mints)
+    }
+
+    pub fn migrate_donation_state(ctx: Context<MigrateDonationState>) -> Result<()> {
+        ctx.accounts.migrate_donation_state()
+    }
+
+    pub fn set_charity_match_ratio(ctx: Context<UpdateCharityConfig>, match_ratio_bps: Option<u16>) -> Result<()> {
+        ctx.accounts.set_charity_match_ratio(match_ratio_bps, ctx.bumps)
+    }
+
+    pub fn create_campaign(ctx: Context<CreateCampaign>, id: u64, args: CampaignArgs) -> Result<()> {
+        ctx.accounts.create_campaign(id, args, ctx.bumps)
+    }
+
+    pub fn reduce_campaign_budget(ctx: Context<ReduceCampaignBudget>, lamports: u64) -> Result<()> {
+        ctx.accounts.reduce_campaign_budget(lamports)
+    }
+
+    pub fn close_campaign(ctx: Context<CloseCampaign>) -> Result<()> {
+        ctx.accounts.close_campaign()
+    }
+
+    pub fn donate(ctx: Context<DonateSol>, seeds: u64, sol_donation: u64, allowlist_proof: Option<AllowlistProof>) -> Result<()> {
+        ctx.accounts.donate_sol(seeds, sol_donation, allowlist_proof)
     }
 
     pub fn match_donation(ctx: Context<MatchDonation>) -> Result<()> {
@@ -26,4 +105,32 @@ pub mod bonk_paws {
     pub fn finalize_donation(ctx: Context<FinalizeDonation>) -> Result<()> {
         ctx.accounts.finalize_donation(ctx.bumps)
     }
+
+    pub fn match_batch<'info>(ctx: Context<'_, '_, 'info, 'info, MatchBatch<'info>>) -> Result<()> {
+        ctx.accounts.match_batch(ctx.remaining_accounts, ctx.bumps)
+    }
+
+    pub fn finalize_batch<'info>(ctx: Context<'_, '_, 'info, 'info, FinalizeBatch<'info>>) -> Result<()> {
+        ctx.accounts.finalize_batch(ctx.remaining_accounts, ctx.bumps)
+    }
+
+    pub fn fund_sol_treasury(ctx: Context<FundSolTreasury>, lamports: u64) -> Result<()> {
+        ctx.accounts.fund_sol_treasury(lamports, ctx.bumps)
+    }
+
+    pub fn withdraw_sol_treasury(ctx: Context<WithdrawSolTreasury>, lamports: u64) -> Result<()> {
+        ctx.accounts.withdraw_sol_treasury(lamports)
+    }
+
+    pub fn match_from_treasury(ctx: Context<MatchFromTreasury>) -> Result<()> {
+        ctx.accounts.match_from_treasury()
+    }
+
+    pub fn expire_match(ctx: Context<ExpireMatch>) -> Result<()> {
+        ctx.accounts.expire_match()
+    }
+
+    pub fn cancel_match(ctx: Context<CancelMatch>) -> Result<()> {
+        ctx.accounts.cancel_match()
+    }
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 pub mod require_discriminator_eq;
-pub use require_discriminator_eq::*;
+pub use crate::require_discriminator_eq;
 
 pub mod require_instruction_eq;
-pub use require_instruction_eq::*;
\ No newline at end of file
+pub use crate::require_instruction_eq;
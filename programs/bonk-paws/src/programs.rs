@@ -1,6 +1,53 @@
-use anchor_lang::{prelude::*, Discriminator};
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction, Discriminator};
+
+use crate::{constants::MAX_SLIPPAGE_BPS, errors::BonkPawsError, state::Config};
+
+/*
+
+    Swap Venues
+
+    A swap venue parses a swap instruction of its program into the mints,
+    token accounts, amounts and slippage that we verify a match against,
+    along with any checks of its own. Jupiter is our primary venue, while
+    a simple constant product pool program serves as a venue for local
+    tests and as a fallback.
+
+*/
+pub trait SwapVenue {
+    // An ExactIn swap sells an exact amount, otherwise it buys an exact amount
+    fn is_exact_in(&self) -> bool;
+    // The source tokens quoted for the swap, before slippage
+    fn quoted_in_amount(&self) -> u64;
+    // The destination tokens quoted for the swap, before slippage
+    fn quoted_out_amount(&self) -> u64;
+    fn slippage_bps(&self) -> u16;
+    // The most source tokens the swap can spend
+    fn max_in_amount(&self) -> Result<u64>;
+    // The fewest destination tokens the swap can return
+    fn min_out_amount(&self) -> Result<u64>;
+    fn token_program(&self) -> Pubkey;
+    fn user_transfer_authority(&self) -> Pubkey;
+    fn source_mint(&self) -> Pubkey;
+    fn source_token_account(&self) -> Pubkey;
+    fn destination_mint(&self) -> Pubkey;
+    fn destination_token_account(&self) -> Pubkey;
+    // Checks specific to the venue
+    fn validate(&self, config: &Config) -> Result<()>;
+}
+
+pub fn swap_venue(ix: &Instruction) -> Result<Box<dyn SwapVenue + '_>> {
+    if jupiter::check_id(&ix.program_id) {
+        Ok(Box::new(jupiter::Route::try_from(ix)?))
+    } else if constant_product::check_id(&ix.program_id) {
+        Ok(Box::new(constant_product::Swap::try_from(ix)?))
+    } else {
+        Err(BonkPawsError::InvalidInstruction.into())
+    }
+}
+
 pub mod jupiter {
     use super::*;
+    use solana_program::pubkey;
     declare_id!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
 
     #[derive(AnchorDeserialize, AnchorSerialize)]
@@ -124,6 +171,303 @@ pub mod jupiter {
         }
     }
 
+    pub struct ExactOutRouteAccountMetas<'info> {
+        pub token_program: &'info AccountMeta,
+        pub user_transfer_authority: &'info AccountMeta,
+        pub user_source_token_account: &'info AccountMeta,
+        pub user_destination_token_account: &'info AccountMeta,
+        pub destination_token_account: Option<&'info AccountMeta>,
+        pub source_mint: &'info AccountMeta,
+        pub destination_mint: &'info AccountMeta,
+        pub platform_fee_account: Option<&'info AccountMeta>,
+        pub token2022_program: Option<&'info AccountMeta>,
+        pub event_authority: &'info AccountMeta,
+        pub program: &'info AccountMeta,
+    }
+
+    impl<'info> TryFrom<&'info Vec<AccountMeta>> for ExactOutRouteAccountMetas<'info> {
+        type Error = Error;
+    
+        fn try_from(value: &'info Vec<AccountMeta>) -> Result<Self> {
+            if value.len() < 11 {
+                return Err(ProgramError::NotEnoughAccountKeys.into());
+            }
+
+            let [
+                token_program,
+                user_transfer_authority,
+                user_source_token_account,
+                user_destination_token_account,
+                destination_token_account,
+                source_mint,
+                destination_mint,
+                platform_fee_account,
+                token2022_program,
+                event_authority,
+                program,
+            ] = [
+                &value[0],
+                &value[1],
+                &value[2],
+                &value[3],
+                &value[4],
+                &value[5],
+                &value[6],
+                &value[7],
+                &value[8],
+                &value[9],
+                &value[10]
+            ];
+
+            let destination_token_account = match destination_token_account.pubkey.eq(&ID) {
+                true => None,
+                false => Some(destination_token_account),
+            };
+    
+            let platform_fee_account = match platform_fee_account.pubkey.eq(&ID) {
+                true => None,
+                false => Some(platform_fee_account),
+            };
+
+            let token2022_program = match token2022_program.pubkey.eq(&ID) {
+                true => None,
+                false => Some(token2022_program),
+            };
+    
+            let accounts = ExactOutRouteAccountMetas {
+                token_program,
+                user_transfer_authority,
+                user_source_token_account,
+                user_destination_token_account,
+                destination_token_account,
+                source_mint,
+                destination_mint,
+                platform_fee_account,
+                token2022_program,
+                event_authority,
+                program,
+            };
+    
+            Ok(accounts)
+        }
+    }
+
+    // Jupiter's PDAs, precomputed to save deriving them on-chain
+    pub const PROGRAM_AUTHORITIES: [Pubkey; 8] = [
+        pubkey!("GGztQqQ6pCPaJQnNpXBgELr5cs3WwDakRbh1iEMzjgSJ"),
+        pubkey!("2MFoS3MPtvyQ4Wh4M9pdfPjz6UhVoNbFbGJAskCPCj3h"),
+        pubkey!("BQ72nSv9f3PRyRKCBnHLVrerrv37CYTHm5h3s9VSGQDV"),
+        pubkey!("6U91aKa8pmMxkJwBCfPTmUEfZi6dHe7DcFq2ALvB2tbB"),
+        pubkey!("4xDsmeTWPNjgSVSS1VTfzFq3iHZhp77ffPkAmkZkdu71"),
+        pubkey!("CapuXNQoDviLvU1PxFiizLgPNQCxrsag1uMeyk6zLVps"),
+        pubkey!("9nnLbotNTcUhvbrsA6Mdkx45Sm82G35zo28AqUvjExn8"),
+        pubkey!("6LXutJvKUw8Q5ue2gCgKHQdAN4suWW8awzFVC6XCguFx"),
+    ];
+    pub const EVENT_AUTHORITY: Pubkey = pubkey!("D8cy77BBepLMngZx6ZukaTff5hCt1HrWyKk3Hnd9oitf");
+
+    pub fn program_authority(id: u8) -> Result<Pubkey> {
+        PROGRAM_AUTHORITIES.get(id as usize).copied().ok_or(BonkPawsError::InvalidJupiterAuthority.into())
+    }
+
+    // Shared accounts routes use the same accounts regardless of swap mode
+    pub type SharedAccountsRouteAccountMetas<'info> = SharedAccountsExactOutRouteAccountMetas<'info>;
+
+    pub enum Route<'info> {
+        SharedAccountsRoute(SharedAccountsRoute, SharedAccountsRouteAccountMetas<'info>),
+        SharedAccountsExactOutRoute(SharedAccountsExactOutRoute, SharedAccountsExactOutRouteAccountMetas<'info>),
+        ExactOutRoute(ExactOutRoute, ExactOutRouteAccountMetas<'info>),
+    }
+
+    impl<'info> TryFrom<&'info Instruction> for Route<'info> {
+        type Error = Error;
+
+        fn try_from(ix: &'info Instruction) -> Result<Self> {
+            require_keys_eq!(ix.program_id, ID, BonkPawsError::InvalidInstruction);
+            require_gte!(ix.data.len(), 8, BonkPawsError::InvalidInstruction);
+
+            let discriminator: [u8; 8] = ix.data[0..8].try_into().map_err(|_| BonkPawsError::InvalidInstruction)?;
+            let route = match discriminator {
+                SharedAccountsRoute::DISCRIMINATOR => Route::SharedAccountsRoute(
+                    SharedAccountsRoute::try_from_slice(&ix.data[8..])?,
+                    SharedAccountsRouteAccountMetas::try_from(&ix.accounts)?,
+                ),
+                SharedAccountsExactOutRoute::DISCRIMINATOR => Route::SharedAccountsExactOutRoute(
+                    SharedAccountsExactOutRoute::try_from_slice(&ix.data[8..])?,
+                    SharedAccountsExactOutRouteAccountMetas::try_from(&ix.accounts)?,
+                ),
+                ExactOutRoute::DISCRIMINATOR => Route::ExactOutRoute(
+                    ExactOutRoute::try_from_slice(&ix.data[8..])?,
+                    ExactOutRouteAccountMetas::try_from(&ix.accounts)?,
+                ),
+                _ => return Err(BonkPawsError::InvalidInstruction.into()),
+            };
+
+            Ok(route)
+        }
+    }
+
+    impl<'info> Route<'info> {
+        pub fn route_plan(&self) -> &[RoutePlanStep] {
+            match self {
+                Route::SharedAccountsRoute(data, _) => &data.route_plan,
+                Route::SharedAccountsExactOutRoute(data, _) => &data.route_plan,
+                Route::ExactOutRoute(data, _) => &data.route_plan,
+            }
+        }
+
+        pub fn platform_fee_bps(&self) -> u8 {
+            match self {
+                Route::SharedAccountsRoute(data, _) => data.platform_fee_bps,
+                Route::SharedAccountsExactOutRoute(data, _) => data.platform_fee_bps,
+                Route::ExactOutRoute(data, _) => data.platform_fee_bps,
+            }
+        }
+
+        pub fn platform_fee_account(&self) -> Option<Pubkey> {
+            match self {
+                Route::SharedAccountsRoute(_, accounts) => accounts.platform_fee_account.map(|account| account.pubkey),
+                Route::SharedAccountsExactOutRoute(_, accounts) => accounts.platform_fee_account.map(|account| account.pubkey),
+                Route::ExactOutRoute(_, accounts) => accounts.platform_fee_account.map(|account| account.pubkey),
+            }
+        }
+
+        // Ensure Jupiter's own accounts are the PDAs we expect
+        pub fn validate_authorities(&self) -> Result<()> {
+            let (event_authority, program) = match self {
+                Route::SharedAccountsRoute(data, accounts) => {
+                    require_keys_eq!(accounts.program_authority.pubkey, program_authority(data.id)?, BonkPawsError::InvalidJupiterAuthority);
+                    (accounts.event_authority.pubkey, accounts.program.pubkey)
+                },
+                Route::SharedAccountsExactOutRoute(data, accounts) => {
+                    require_keys_eq!(accounts.program_authority.pubkey, program_authority(data.id)?, BonkPawsError::InvalidJupiterAuthority);
+                    (accounts.event_authority.pubkey, accounts.program.pubkey)
+                },
+                Route::ExactOutRoute(_, accounts) => (accounts.event_authority.pubkey, accounts.program.pubkey),
+            };
+            require_keys_eq!(event_authority, EVENT_AUTHORITY, BonkPawsError::InvalidJupiterAuthority);
+            require_keys_eq!(program, ID, BonkPawsError::InvalidJupiterAuthority);
+
+            Ok(())
+        }
+    }
+
+    impl<'info> SwapVenue for Route<'info> {
+        fn is_exact_in(&self) -> bool {
+            matches!(self, Route::SharedAccountsRoute(..))
+        }
+
+        fn quoted_in_amount(&self) -> u64 {
+            match self {
+                Route::SharedAccountsRoute(data, _) => data.in_amount,
+                Route::SharedAccountsExactOutRoute(data, _) => data.quoted_in_amount,
+                Route::ExactOutRoute(data, _) => data.quoted_in_amount,
+            }
+        }
+
+        fn quoted_out_amount(&self) -> u64 {
+            match self {
+                Route::SharedAccountsRoute(data, _) => data.quoted_out_amount,
+                Route::SharedAccountsExactOutRoute(data, _) => data.out_amount,
+                Route::ExactOutRoute(data, _) => data.out_amount,
+            }
+        }
+
+        fn slippage_bps(&self) -> u16 {
+            match self {
+                Route::SharedAccountsRoute(data, _) => data.slippage_bps,
+                Route::SharedAccountsExactOutRoute(data, _) => data.slippage_bps,
+                Route::ExactOutRoute(data, _) => data.slippage_bps,
+            }
+        }
+
+        fn max_in_amount(&self) -> Result<u64> {
+            match self {
+                Route::SharedAccountsRoute(data, _) => Ok(data.in_amount),
+                // The route's slippage is clamped so a bad quote can never raise the cap
+                Route::SharedAccountsExactOutRoute(data, _) => data.quoted_in_amount
+                    .checked_mul(10000 + data.slippage_bps.min(MAX_SLIPPAGE_BPS) as u64).ok_or(BonkPawsError::Overflow)?
+                    .checked_div(10000).ok_or(BonkPawsError::Overflow.into()),
+                Route::ExactOutRoute(data, _) => data.quoted_in_amount
+                    .checked_mul(10000 + data.slippage_bps.min(MAX_SLIPPAGE_BPS) as u64).ok_or(BonkPawsError::Overflow)?
+                    .checked_div(10000).ok_or(BonkPawsError::Overflow.into()),
+            }
+        }
+
+        fn min_out_amount(&self) -> Result<u64> {
+            match self {
+                Route::SharedAccountsRoute(data, _) => data.quoted_out_amount
+                    .checked_mul(10000u64.checked_sub(data.slippage_bps as u64).ok_or(BonkPawsError::Overflow)?).ok_or(BonkPawsError::Overflow)?
+                    .checked_div(10000).ok_or(BonkPawsError::Overflow.into()),
+                Route::SharedAccountsExactOutRoute(data, _) => Ok(data.out_amount),
+                Route::ExactOutRoute(data, _) => Ok(data.out_amount),
+            }
+        }
+
+        fn token_program(&self) -> Pubkey {
+            match self {
+                Route::SharedAccountsRoute(_, accounts) => accounts.token_program.pubkey,
+                Route::SharedAccountsExactOutRoute(_, accounts) => accounts.token_program.pubkey,
+                Route::ExactOutRoute(_, accounts) => accounts.token_program.pubkey,
+            }
+        }
+
+        fn user_transfer_authority(&self) -> Pubkey {
+            match self {
+                Route::SharedAccountsRoute(_, accounts) => accounts.user_transfer_authority.pubkey,
+                Route::SharedAccountsExactOutRoute(_, accounts) => accounts.user_transfer_authority.pubkey,
+                Route::ExactOutRoute(_, accounts) => accounts.user_transfer_authority.pubkey,
+            }
+        }
+
+        fn source_mint(&self) -> Pubkey {
+            match self {
+                Route::SharedAccountsRoute(_, accounts) => accounts.source_mint.pubkey,
+                Route::SharedAccountsExactOutRoute(_, accounts) => accounts.source_mint.pubkey,
+                Route::ExactOutRoute(_, accounts) => accounts.source_mint.pubkey,
+            }
+        }
+
+        fn source_token_account(&self) -> Pubkey {
+            match self {
+                Route::SharedAccountsRoute(_, accounts) => accounts.source_token_account.pubkey,
+                Route::SharedAccountsExactOutRoute(_, accounts) => accounts.source_token_account.pubkey,
+                Route::ExactOutRoute(_, accounts) => accounts.user_source_token_account.pubkey,
+            }
+        }
+
+        fn destination_mint(&self) -> Pubkey {
+            match self {
+                Route::SharedAccountsRoute(_, accounts) => accounts.destination_mint.pubkey,
+                Route::SharedAccountsExactOutRoute(_, accounts) => accounts.destination_mint.pubkey,
+                Route::ExactOutRoute(_, accounts) => accounts.destination_mint.pubkey,
+            }
+        }
+
+        fn destination_token_account(&self) -> Pubkey {
+            match self {
+                Route::SharedAccountsRoute(_, accounts) => accounts.destination_token_account.pubkey,
+                Route::SharedAccountsExactOutRoute(_, accounts) => accounts.destination_token_account.pubkey,
+                // Without a destination token account, the output goes to the user's account
+                Route::ExactOutRoute(_, accounts) => accounts.destination_token_account
+                    .unwrap_or(accounts.user_destination_token_account)
+                    .pubkey,
+            }
+        }
+
+        // Route plan, platform fee and Jupiter authority checks
+        fn validate(&self, config: &Config) -> Result<()> {
+            config.route_policy.validate(self.route_plan())?;
+
+            require_eq!(self.platform_fee_bps(), 0, BonkPawsError::InvalidPlatformFee);
+            if let Some(platform_fee_account) = self.platform_fee_account() {
+                require_keys_neq!(config.platform_fee_account, Pubkey::default(), BonkPawsError::InvalidPlatformFee);
+                require_keys_eq!(platform_fee_account, config.platform_fee_account, BonkPawsError::InvalidPlatformFee);
+            }
+
+            self.validate_authorities()
+        }
+    }
+
     impl Discriminator for SharedAccountsRoute {
         const DISCRIMINATOR: [u8; 8] = [0xc1, 0x20, 0x9b, 0x33, 0x41, 0xd6, 0x9c, 0x81];
     }
@@ -186,4 +530,521 @@ pub mod jupiter {
         OpenBookV2 { side: Side },
         RaydiumClmmV2,
     }
-}
\ No newline at end of file
+
+    impl Swap {
+        // The Borsh variant index, used to identify AMMs in route policies
+        pub fn index(&self) -> Result<u8> {
+            Ok(self.try_to_vec()?[0])
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::programs::tests::discriminator;
+
+        #[test]
+        fn program_authorities_match_derived_pdas() {
+            for id in 0..=u8::MAX {
+                match PROGRAM_AUTHORITIES.get(id as usize) {
+                    Some(authority) => assert_eq!(*authority, Pubkey::find_program_address(&[b"authority", &[id]], &ID).0),
+                    None => assert_eq!(program_authority(id).err(), Some(BonkPawsError::InvalidJupiterAuthority.into())),
+                }
+            }
+        }
+
+        #[test]
+        fn event_authority_matches_derived_pda() {
+            assert_eq!(EVENT_AUTHORITY, Pubkey::find_program_address(&[b"__event_authority"], &ID).0);
+        }
+
+        #[test]
+        fn discriminators_match_instruction_names() {
+            assert_eq!(SharedAccountsRoute::DISCRIMINATOR, discriminator("shared_accounts_route"));
+            assert_eq!(ExactOutRoute::DISCRIMINATOR, discriminator("exact_out_route"));
+            assert_eq!(SharedAccountsExactOutRoute::DISCRIMINATOR, discriminator("shared_accounts_exact_out_route"));
+        }
+
+        #[test]
+        fn exact_out_route_is_parsed() {
+            let keys: Vec<Pubkey> = (0..11).map(|_| Pubkey::new_unique()).collect();
+            let mut accounts: Vec<AccountMeta> = keys.iter().map(|key| AccountMeta::new(*key, false)).collect();
+            // Jupiter's program id marks the optional accounts as absent
+            for i in [4, 7, 8] {
+                accounts[i].pubkey = ID;
+            }
+            let data = ExactOutRoute { route_plan: vec![], out_amount: 1_000, quoted_in_amount: 2_000, slippage_bps: 50, platform_fee_bps: 0 };
+            let ix = Instruction { program_id: ID, accounts, data: [&ExactOutRoute::DISCRIMINATOR[..], &data.try_to_vec().unwrap()].concat() };
+
+            let swap = swap_venue(&ix).unwrap();
+            assert!(!swap.is_exact_in());
+            assert_eq!(swap.min_out_amount().unwrap(), 1_000);
+            assert_eq!(swap.quoted_in_amount(), 2_000);
+            assert_eq!(swap.max_in_amount().unwrap(), 2_010);
+            assert_eq!(swap.source_mint(), keys[5]);
+            assert_eq!(swap.destination_mint(), keys[6]);
+            // Without a destination token account, the output goes to the user's account
+            assert_eq!(swap.destination_token_account(), keys[3]);
+        }
+    }
+}
+
+pub mod constant_product {
+    use super::*;
+    declare_id!("8BCsX7s7WzLWeESvgou13YAZ689oTLRhLxrrBh5jDb1o");
+
+    #[derive(AnchorDeserialize, AnchorSerialize)]
+    pub struct SwapExactIn {
+        pub amount_in: u64,
+        pub quoted_out_amount: u64,
+        pub slippage_bps: u16,
+    }
+
+    #[derive(AnchorDeserialize, AnchorSerialize)]
+    pub struct SwapExactOut {
+        pub amount_out: u64,
+        pub quoted_in_amount: u64,
+        pub slippage_bps: u16,
+    }
+
+    pub struct SwapAccountMetas<'info> {
+        pub pool: &'info AccountMeta,
+        pub user_transfer_authority: &'info AccountMeta,
+        pub source_token_account: &'info AccountMeta,
+        pub destination_token_account: &'info AccountMeta,
+        pub pool_source_vault: &'info AccountMeta,
+        pub pool_destination_vault: &'info AccountMeta,
+        pub source_mint: &'info AccountMeta,
+        pub destination_mint: &'info AccountMeta,
+        pub token_program: &'info AccountMeta,
+    }
+
+    impl<'info> TryFrom<&'info Vec<AccountMeta>> for SwapAccountMetas<'info> {
+        type Error = Error;
+
+        fn try_from(value: &'info Vec<AccountMeta>) -> Result<Self> {
+            let [
+                pool,
+                user_transfer_authority,
+                source_token_account,
+                destination_token_account,
+                pool_source_vault,
+                pool_destination_vault,
+                source_mint,
+                destination_mint,
+                token_program,
+            ] = value.as_slice() else {
+                return Err(ProgramError::NotEnoughAccountKeys.into());
+            };
+
+            Ok(Self {
+                pool,
+                user_transfer_authority,
+                source_token_account,
+                destination_token_account,
+                pool_source_vault,
+                pool_destination_vault,
+                source_mint,
+                destination_mint,
+                token_program,
+            })
+        }
+    }
+
+    // Each pair of mints has a single pool, seeded by the sorted mints
+    pub fn pool(mint: &Pubkey, other_mint: &Pubkey) -> Pubkey {
+        let (mint_a, mint_b) = match mint < other_mint {
+            true => (mint, other_mint),
+            false => (other_mint, mint),
+        };
+        Pubkey::find_program_address(&[b"pool", mint_a.as_ref(), mint_b.as_ref()], &ID).0
+    }
+
+    pub enum Swap<'info> {
+        ExactIn(SwapExactIn, SwapAccountMetas<'info>),
+        ExactOut(SwapExactOut, SwapAccountMetas<'info>),
+    }
+
+    impl<'info> TryFrom<&'info Instruction> for Swap<'info> {
+        type Error = Error;
+
+        fn try_from(ix: &'info Instruction) -> Result<Self> {
+            require_keys_eq!(ix.program_id, ID, BonkPawsError::InvalidInstruction);
+            require_gte!(ix.data.len(), 8, BonkPawsError::InvalidInstruction);
+
+            let discriminator: [u8; 8] = ix.data[0..8].try_into().map_err(|_| BonkPawsError::InvalidInstruction)?;
+            let swap = match discriminator {
+                SwapExactIn::DISCRIMINATOR => Swap::ExactIn(
+                    SwapExactIn::try_from_slice(&ix.data[8..])?,
+                    SwapAccountMetas::try_from(&ix.accounts)?,
+                ),
+                SwapExactOut::DISCRIMINATOR => Swap::ExactOut(
+                    SwapExactOut::try_from_slice(&ix.data[8..])?,
+                    SwapAccountMetas::try_from(&ix.accounts)?,
+                ),
+                _ => return Err(BonkPawsError::InvalidInstruction.into()),
+            };
+
+            Ok(swap)
+        }
+    }
+
+    impl<'info> Swap<'info> {
+        fn accounts(&self) -> &SwapAccountMetas<'info> {
+            match self {
+                Swap::ExactIn(_, accounts) => accounts,
+                Swap::ExactOut(_, accounts) => accounts,
+            }
+        }
+    }
+
+    impl<'info> SwapVenue for Swap<'info> {
+        fn is_exact_in(&self) -> bool {
+            matches!(self, Swap::ExactIn(..))
+        }
+
+        fn quoted_in_amount(&self) -> u64 {
+            match self {
+                Swap::ExactIn(data, _) => data.amount_in,
+                Swap::ExactOut(data, _) => data.quoted_in_amount,
+            }
+        }
+
+        fn quoted_out_amount(&self) -> u64 {
+            match self {
+                Swap::ExactIn(data, _) => data.quoted_out_amount,
+                Swap::ExactOut(data, _) => data.amount_out,
+            }
+        }
+
+        fn slippage_bps(&self) -> u16 {
+            match self {
+                Swap::ExactIn(data, _) => data.slippage_bps,
+                Swap::ExactOut(data, _) => data.slippage_bps,
+            }
+        }
+
+        // Unlike Jupiter, the pool bounds an ExactOut swap's input by its slippage
+        fn max_in_amount(&self) -> Result<u64> {
+            match self {
+                Swap::ExactIn(data, _) => Ok(data.amount_in),
+                Swap::ExactOut(data, _) => data.quoted_in_amount
+                    .checked_mul(10000u64.checked_add(data.slippage_bps as u64).ok_or(BonkPawsError::Overflow)?).ok_or(BonkPawsError::Overflow)?
+                    .checked_div(10000).ok_or(BonkPawsError::Overflow.into()),
+            }
+        }
+
+        fn min_out_amount(&self) -> Result<u64> {
+            match self {
+                Swap::ExactIn(data, _) => data.quoted_out_amount
+                    .checked_mul(10000u64.checked_sub(data.slippage_bps as u64).ok_or(BonkPawsError::Overflow)?).ok_or(BonkPawsError::Overflow)?
+                    .checked_div(10000).ok_or(BonkPawsError::Overflow.into()),
+                Swap::ExactOut(data, _) => Ok(data.amount_out),
+            }
+        }
+
+        fn token_program(&self) -> Pubkey {
+            self.accounts().token_program.pubkey
+        }
+
+        fn user_transfer_authority(&self) -> Pubkey {
+            self.accounts().user_transfer_authority.pubkey
+        }
+
+        fn source_mint(&self) -> Pubkey {
+            self.accounts().source_mint.pubkey
+        }
+
+        fn source_token_account(&self) -> Pubkey {
+            self.accounts().source_token_account.pubkey
+        }
+
+        fn destination_mint(&self) -> Pubkey {
+            self.accounts().destination_mint.pubkey
+        }
+
+        fn destination_token_account(&self) -> Pubkey {
+            self.accounts().destination_token_account.pubkey
+        }
+
+        // The pool program checks its own vaults, we only need the pool to be the pair's.
+        // As a pool's price is easily moved, only allow-listed pools may be swapped on
+        // and only while quotes are checked against the oracle.
+        fn validate(&self, config: &Config) -> Result<()> {
+            let accounts = self.accounts();
+            require!(config.route_policy.pools.contains(&accounts.pool.pubkey), BonkPawsError::PoolNotAllowed);
+            require!(config.oracle_policy.is_enabled(), BonkPawsError::InvalidOracle);
+            require_keys_eq!(
+                accounts.pool.pubkey,
+                pool(&accounts.source_mint.pubkey, &accounts.destination_mint.pubkey),
+                BonkPawsError::InvalidPool
+            );
+
+            Ok(())
+        }
+    }
+
+    impl Discriminator for SwapExactIn {
+        const DISCRIMINATOR: [u8; 8] = [0x68, 0x68, 0x83, 0x56, 0xa1, 0xbd, 0xb4, 0xd8];
+    }
+
+    impl Discriminator for SwapExactOut {
+        const DISCRIMINATOR: [u8; 8] = [0xfa, 0x49, 0x65, 0x21, 0x26, 0xcf, 0x4b, 0xb8];
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::{
+            programs::tests::discriminator,
+            state::{ApprovalScheme, KeeperPolicy, MatchCaps, OraclePolicy, RoutePolicy, SurplusPolicy},
+        };
+
+        struct Keys {
+            source_mint: Pubkey,
+            destination_mint: Pubkey,
+            pool: Pubkey,
+        }
+
+        fn keys() -> Keys {
+            let (source_mint, destination_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+            Keys { source_mint, destination_mint, pool: pool(&source_mint, &destination_mint) }
+        }
+
+        fn swap_ix(keys: &Keys, data: Vec<u8>) -> Instruction {
+            let mut accounts: Vec<AccountMeta> = (0..9).map(|_| AccountMeta::new(Pubkey::new_unique(), false)).collect();
+            accounts[0].pubkey = keys.pool;
+            accounts[6].pubkey = keys.source_mint;
+            accounts[7].pubkey = keys.destination_mint;
+            Instruction { program_id: ID, accounts, data }
+        }
+
+        fn exact_in(amount_in: u64, quoted_out_amount: u64, slippage_bps: u16) -> Vec<u8> {
+            let data = SwapExactIn { amount_in, quoted_out_amount, slippage_bps };
+            [&SwapExactIn::DISCRIMINATOR[..], &data.try_to_vec().unwrap()].concat()
+        }
+
+        fn exact_out(amount_out: u64, quoted_in_amount: u64, slippage_bps: u16) -> Vec<u8> {
+            let data = SwapExactOut { amount_out, quoted_in_amount, slippage_bps };
+            [&SwapExactOut::DISCRIMINATOR[..], &data.try_to_vec().unwrap()].concat()
+        }
+
+        fn config(pools: Vec<Pubkey>, enabled: bool) -> Config {
+            Config {
+                admin: Pubkey::new_unique(),
+                approval_scheme: ApprovalScheme::Ed25519,
+                merkle_root: [0u8; 32],
+                route_policy: RoutePolicy { pools, ..RoutePolicy::default() },
+                oracle_policy: OraclePolicy { enabled, max_deviation_bps: 100, max_staleness_slots: 10 },
+                platform_fee_account: Pubkey::default(),
+                keeper_policy: KeeperPolicy::default(),
+                match_ratio_bps: 10_000,
+                match_caps: MatchCaps::default(),
+                match_ttl_seconds: 0,
+                max_reserved_lamports: 0,
+                surplus_policy: SurplusPolicy::default(),
+                campaign: None,
+                vault_mints: vec![],
+                bump: 255,
+            }
+        }
+
+        #[test]
+        fn discriminators_match_instruction_names() {
+            assert_eq!(SwapExactIn::DISCRIMINATOR, discriminator("swap_exact_in"));
+            assert_eq!(SwapExactOut::DISCRIMINATOR, discriminator("swap_exact_out"));
+        }
+
+        #[test]
+        fn exact_in_swaps_are_parsed() {
+            let keys = keys();
+            let ix = swap_ix(&keys, exact_in(1_000, 2_000, 50));
+
+            let swap = swap_venue(&ix).unwrap();
+            assert!(swap.is_exact_in());
+            assert_eq!(swap.max_in_amount().unwrap(), 1_000);
+            // 2000 less 0.5% slippage
+            assert_eq!(swap.min_out_amount().unwrap(), 1_990);
+            assert_eq!(swap.quoted_out_amount(), 2_000);
+            assert_eq!(swap.source_mint(), keys.source_mint);
+            assert_eq!(swap.destination_mint(), keys.destination_mint);
+            assert_eq!(swap.source_token_account(), ix.accounts[2].pubkey);
+            assert_eq!(swap.destination_token_account(), ix.accounts[3].pubkey);
+        }
+
+        #[test]
+        fn exact_out_swaps_are_parsed() {
+            let keys = keys();
+            let ix = swap_ix(&keys, exact_out(1_000, 2_000, 50));
+
+            let swap = swap_venue(&ix).unwrap();
+            assert!(!swap.is_exact_in());
+            assert_eq!(swap.min_out_amount().unwrap(), 1_000);
+            // 2000 plus 0.5% slippage
+            assert_eq!(swap.max_in_amount().unwrap(), 2_010);
+            assert_eq!(swap.quoted_in_amount(), 2_000);
+        }
+
+        #[test]
+        fn unknown_instructions_are_rejected() {
+            let keys = keys();
+            let invalid_instruction = Some(BonkPawsError::InvalidInstruction.into());
+
+            // Another program
+            let mut ix = swap_ix(&keys, exact_in(1_000, 2_000, 50));
+            ix.program_id = Pubkey::new_unique();
+            assert_eq!(swap_venue(&ix).err(), invalid_instruction);
+
+            // Another instruction of the pool program
+            let ix = swap_ix(&keys, [0u8; 8].to_vec());
+            assert_eq!(swap_venue(&ix).err(), invalid_instruction);
+
+            // A truncated discriminator
+            let ix = swap_ix(&keys, SwapExactIn::DISCRIMINATOR[..4].to_vec());
+            assert_eq!(swap_venue(&ix).err(), invalid_instruction);
+        }
+
+        #[test]
+        fn swaps_need_every_account() {
+            let keys = keys();
+            let mut ix = swap_ix(&keys, exact_in(1_000, 2_000, 50));
+            ix.accounts.pop();
+
+            assert_eq!(swap_venue(&ix).err(), Some(ProgramError::NotEnoughAccountKeys.into()));
+        }
+
+        #[test]
+        fn only_allowed_pools_with_an_oracle_are_valid() {
+            let keys = keys();
+            let ix = swap_ix(&keys, exact_in(1_000, 2_000, 50));
+            let swap = swap_venue(&ix).unwrap();
+
+            assert!(swap.validate(&config(vec![keys.pool], true)).is_ok());
+            assert_eq!(swap.validate(&config(vec![], true)).err(), Some(BonkPawsError::PoolNotAllowed.into()));
+            assert_eq!(swap.validate(&config(vec![keys.pool], false)).err(), Some(BonkPawsError::InvalidOracle.into()));
+        }
+
+        #[test]
+        fn pools_must_be_the_pairs() {
+            let keys = keys();
+            let other_pool = pool(&keys.source_mint, &Pubkey::new_unique());
+            let mut ix = swap_ix(&keys, exact_in(1_000, 2_000, 50));
+            ix.accounts[0].pubkey = other_pool;
+            let swap = swap_venue(&ix).unwrap();
+
+            assert_eq!(
+                swap.validate(&config(vec![other_pool], true)).err(),
+                Some(BonkPawsError::InvalidPool.into())
+            );
+        }
+    }
+}
+
+pub mod pyth {
+    use super::*;
+
+    /*
+
+        Pyth Price Account
+
+        A minimal reader for the aggregate price of a Pyth (v2) price 
+        account. Only the fields we need are decoded, which also allows a
+        locally created account with the same layout to stand in for a 
+        real price feed in tests.
+
+    */
+
+    pub const MAGIC: u32 = 0xa1b2c3d4;
+    pub const STATUS_TRADING: u32 = 1;
+
+    pub struct PriceAccount {
+        pub expo: i32,
+        pub price: i64,
+        pub conf: u64,
+        pub status: u32,
+        pub pub_slot: u64,
+    }
+
+    impl<'info> TryFrom<&AccountInfo<'info>> for PriceAccount {
+        type Error = Error;
+
+        fn try_from(account: &AccountInfo<'info>) -> Result<Self> {
+            let data = account.try_borrow_data()?;
+            require_gte!(data.len(), 240, BonkPawsError::InvalidOracle);
+
+            let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+            let read_u64 = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+
+            require_eq!(read_u32(0), MAGIC, BonkPawsError::InvalidOracle);
+
+            Ok(Self {
+                expo: read_u32(20) as i32,
+                price: read_u64(208) as i64,
+                conf: read_u64(216),
+                status: read_u32(224),
+                pub_slot: read_u64(232),
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // A v2 price account with only the fields we decode set
+        fn price_account_data(magic: u32, expo: i32, price: i64, status: u32, pub_slot: u64) -> Vec<u8> {
+            let mut data = vec![0u8; 240];
+            data[0..4].copy_from_slice(&magic.to_le_bytes());
+            data[20..24].copy_from_slice(&expo.to_le_bytes());
+            data[208..216].copy_from_slice(&price.to_le_bytes());
+            data[216..224].copy_from_slice(&7u64.to_le_bytes());
+            data[224..228].copy_from_slice(&status.to_le_bytes());
+            data[232..240].copy_from_slice(&pub_slot.to_le_bytes());
+            data
+        }
+
+        fn parse(mut data: Vec<u8>) -> Result<PriceAccount> {
+            let (key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let mut lamports = 0;
+            let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+            PriceAccount::try_from(&account)
+        }
+
+        #[test]
+        fn price_account_is_decoded() {
+            let price_account = parse(price_account_data(MAGIC, -8, 2_500_000, STATUS_TRADING, 42)).unwrap();
+
+            assert_eq!(price_account.expo, -8);
+            assert_eq!(price_account.price, 2_500_000);
+            assert_eq!(price_account.conf, 7);
+            assert_eq!(price_account.status, STATUS_TRADING);
+            assert_eq!(price_account.pub_slot, 42);
+        }
+
+        #[test]
+        fn price_account_requires_magic() {
+            assert_eq!(
+                parse(price_account_data(0xdeadbeef, -8, 2_500_000, STATUS_TRADING, 42)).err(),
+                Some(BonkPawsError::InvalidOracle.into())
+            );
+        }
+
+        #[test]
+        fn price_account_requires_v2_length() {
+            let mut data = price_account_data(MAGIC, -8, 2_500_000, STATUS_TRADING, 42);
+            data.truncate(239);
+
+            assert_eq!(parse(data).err(), Some(BonkPawsError::InvalidOracle.into()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::solana_program::hash::hash;
+
+    // Anchor's instruction discriminator
+    pub fn discriminator(name: &str) -> [u8; 8] {
+        hash(format!("global:{}", name).as_bytes()).to_bytes()[..8].try_into().unwrap()
+    }
+}
@@ -1,26 +1,596 @@
 use anchor_lang::prelude::*;
 
+use crate::{
+    constants::{MAX_CAMPAIGN_CHARITIES, MAX_POOLS, MAX_VAULT_MINTS, PRICE_PRECISION, SECONDS_PER_DAY},
+    errors::BonkPawsError,
+    programs::{jupiter::RoutePlanStep, pyth::{PriceAccount, STATUS_TRADING}},
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalScheme {
+    Ed25519,
+    Secp256r1 { authority: [u8; 33] },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AmmFilter {
+    Allow,
+    Deny,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RoutePolicy {
+    pub filter: AmmFilter,
+    // Bitmask of jupiter::Swap variants, indexed by their variant index
+    pub amms: u64,
+    pub max_hops: u8,
+    // Constant product pools matches may swap on, none disables the pool venue
+    pub pools: Vec<Pubkey>,
+}
+
+impl Space for RoutePolicy {
+    const INIT_SPACE: usize = 1 + 8 + 1 + (4 + 32 * MAX_POOLS);
+}
+
+impl Default for RoutePolicy {
+    // Preserve the existing behaviour of accepting any route until configured
+    fn default() -> Self {
+        Self {
+            filter: AmmFilter::Deny,
+            amms: 0,
+            max_hops: u8::MAX,
+            pools: vec![],
+        }
+    }
+}
+
+impl RoutePolicy {
+    pub fn validate(&self, route_plan: &[RoutePlanStep]) -> Result<()> {
+        require_gte!(self.max_hops as usize, route_plan.len(), BonkPawsError::InvalidRoute);
+
+        for step in route_plan {
+            let listed = 1u64
+                .checked_shl(step.swap.index()? as u32)
+                .map(|bit| self.amms & bit != 0)
+                .unwrap_or(false);
+
+            match self.filter {
+                AmmFilter::Allow => require!(listed, BonkPawsError::AmmNotAllowed),
+                AmmFilter::Deny => require!(!listed, BonkPawsError::AmmNotAllowed),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Price accounts are configured per vault mint
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct OraclePolicy {
+    pub enabled: bool,
+    pub max_deviation_bps: u16,
+    pub max_staleness_slots: u64,
+}
+
+impl Space for OraclePolicy {
+    const INIT_SPACE: usize = 1 + 2 + 8;
+}
+
+impl OraclePolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // Value an amount of the source token in destination base units at the oracle price
+    pub fn value(&self, price_account: &PriceAccount, in_amount: u64, in_decimals: u8, out_decimals: u8) -> Result<u128> {
+        require_eq!(price_account.status, STATUS_TRADING, BonkPawsError::InvalidOracle);
+        require!(price_account.price > 0, BonkPawsError::InvalidOracle);
+
+        let slot = Clock::get()?.slot;
+        require_gte!(
+            price_account.pub_slot.checked_add(self.max_staleness_slots).ok_or(BonkPawsError::Overflow)?,
+            slot,
+            BonkPawsError::StaleOracle
+        );
+
+        let exponent = price_account.expo
+            .checked_add(out_decimals as i32).ok_or(BonkPawsError::Overflow)?
+            .checked_sub(in_decimals as i32).ok_or(BonkPawsError::Overflow)?;
+        let scale = 10u128.checked_pow(exponent.unsigned_abs()).ok_or(BonkPawsError::Overflow)?;
+        let value = (in_amount as u128).checked_mul(price_account.price as u128).ok_or(BonkPawsError::Overflow)?;
+        match exponent >= 0 {
+            true => value.checked_mul(scale).ok_or(BonkPawsError::Overflow.into()),
+            false => value.checked_div(scale).ok_or(BonkPawsError::Overflow.into()),
+        }
+    }
+
+    // Compare a quote against the oracle's price of the source token denominated in the destination token
+    pub fn validate(&self, price_account: &PriceAccount, in_amount: u64, in_decimals: u8, out_amount: u64, out_decimals: u8) -> Result<()> {
+        let expected_out_amount = self.value(price_account, in_amount, in_decimals, out_decimals)?;
+        require!(expected_out_amount > 0, BonkPawsError::InvalidOracle);
+
+        let deviation_bps = expected_out_amount
+            .abs_diff(out_amount as u128)
+            .checked_mul(10000).ok_or(BonkPawsError::Overflow)?
+            .checked_div(expected_out_amount).ok_or(BonkPawsError::Overflow)?;
+        require_gte!(self.max_deviation_bps as u128, deviation_bps, BonkPawsError::OraclePriceDeviation);
+
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct KeeperPolicy {
+    // Allow any keeper to match donations, not just our signing authority
+    pub permissionless: bool,
+}
+
+impl Space for KeeperPolicy {
+    const INIT_SPACE: usize = 1;
+}
+
+// A mint the vault may hold, along with its rules for matching
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct VaultMint {
+    pub mint: Pubkey,
+    // Share of the amount spent on a match that is burned, zero for mints we don't burn
+    pub burn_bps: u16,
+    // Paid from the vault to the keeper for each finalized match, in the mint's base units
+    pub keeper_reward: u64,
+    // Pyth style price account of the mint in SOL, used when the oracle is enabled
+    pub price_account: Pubkey,
+}
+
+impl Space for VaultMint {
+    const INIT_SPACE: usize = 32 + 2 + 8 + 32;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct MatchCaps {
+    // Zero disables the respective cap
+    pub donor_cap_lamports: u64,
+    pub donor_window_seconds: i64,
+    pub charity_daily_cap_lamports: u64,
+}
+
+impl Space for MatchCaps {
+    const INIT_SPACE: usize = 8 + 8 + 8;
+}
+
+// Where SOL received beyond what the vault paid for is sent
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SurplusPolicy {
+    #[default]
+    Charity,
+    Vault,
+    Treasury { treasury: Pubkey },
+}
+
+impl Space for SurplusPolicy {
+    const INIT_SPACE: usize = 1 + 32;
+}
+
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub approval_scheme: ApprovalScheme,
+    pub merkle_root: [u8; 32],
+    pub route_policy: RoutePolicy,
+    pub oracle_policy: OraclePolicy,
+    // The default pubkey disallows platform fee accounts on routes
+    pub platform_fee_account: Pubkey,
+    pub keeper_policy: KeeperPolicy,
+    pub match_ratio_bps: u16,
+    pub match_caps: MatchCaps,
+    // Zero disables expiry of pending matches
+    pub match_ttl_seconds: i64,
+    // Lamports pending matches may reserve while the vault can't be valued
+    // without an oracle, zero leaves reservations unbounded
+    pub max_reserved_lamports: u64,
+    pub surplus_policy: SurplusPolicy,
+    // The campaign donors must match under while it is set
+    pub campaign: Option<Pubkey>,
+    // Allow-listed vault mints, in the order keepers should prefer matching from them
+    pub vault_mints: Vec<VaultMint>,
+    pub bump: u8,
+}
+
+impl Space for Config {
+    const INIT_SPACE: usize = 8 + 32 + (1 + 33) + 32 + RoutePolicy::INIT_SPACE + OraclePolicy::INIT_SPACE + 32 + KeeperPolicy::INIT_SPACE + 2 + MatchCaps::INIT_SPACE + 8 + 8 + SurplusPolicy::INIT_SPACE + (1 + 32) + (4 + VaultMint::INIT_SPACE * MAX_VAULT_MINTS) + 1;
+}
+
+impl Config {
+    // Keepers should prefer matching from the first vault mint, though any vault
+    // mint may be matched from. Only its vault is counted towards match capacity.
+    pub fn priority_mint(&self) -> Result<&VaultMint> {
+        self.vault_mints.first().ok_or(BonkPawsError::InvalidVaultMint.into())
+    }
+
+    pub fn vault_mint(&self, mint: &Pubkey) -> Result<&VaultMint> {
+        self.vault_mints
+            .iter()
+            .find(|vault_mint| vault_mint.mint.eq(mint))
+            .ok_or(BonkPawsError::InvalidVaultMint.into())
+    }
+}
+
+#[account]
+pub struct CharityConfig {
+    pub charity: Pubkey,
+    // Overrides the global match ratio for this charity
+    pub match_ratio_bps: u16,
+    pub bump: u8,
+}
+
+impl Space for CharityConfig {
+    const INIT_SPACE: usize = 8 + 32 + 2 + 1;
+}
+
+#[account]
+pub struct Campaign {
+    pub id: u64,
+    pub sponsor: Pubkey,
+    pub start: i64,
+    pub end: i64,
+    pub budget_lamports: u64,
+    pub reserved_lamports: u64,
+    pub match_ratio_bps: u16,
+    // An empty list makes every charity eligible
+    pub charity_ids: Vec<u64>,
+    pub bump: u8,
+}
+
+impl Space for Campaign {
+    const INIT_SPACE: usize = 8 + 8 + 32 + 8 + 8 + 8 + 8 + 2 + (4 + 8 * MAX_CAMPAIGN_CHARITIES) + 1;
+}
+
+impl Campaign {
+    pub fn is_active(&self, timestamp: i64) -> bool {
+        self.start <= timestamp && timestamp < self.end
+    }
+
+    pub fn is_eligible(&self, charity_id: u64) -> bool {
+        self.charity_ids.is_empty() || self.charity_ids.contains(&charity_id)
+    }
+
+    // Reserve budget for a pending match, refusing matches beyond the budget
+    pub fn reserve(&mut self, charity_id: u64, lamports: u64) -> Result<()> {
+        require!(self.is_active(Clock::get()?.unix_timestamp), BonkPawsError::CampaignInactive);
+        require!(self.is_eligible(charity_id), BonkPawsError::CharityNotEligible);
+
+        let reserved_lamports = self.reserved_lamports.checked_add(lamports).ok_or(BonkPawsError::Overflow)?;
+        require_gte!(self.budget_lamports, reserved_lamports, BonkPawsError::CampaignBudgetExceeded);
+        self.reserved_lamports = reserved_lamports;
+
+        Ok(())
+    }
+
+    pub fn release(&mut self, lamports: u64) {
+        self.reserved_lamports = self.reserved_lamports.saturating_sub(lamports);
+    }
+
+    // Lower the budget by lamports not yet reserved for matches
+    pub fn reduce_budget(&mut self, lamports: u64) -> Result<()> {
+        let budget_lamports = self.budget_lamports.checked_sub(lamports).ok_or(BonkPawsError::CampaignBudgetExceeded)?;
+        require_gte!(budget_lamports, self.reserved_lamports, BonkPawsError::CampaignBudgetExceeded);
+        self.budget_lamports = budget_lamports;
+
+        Ok(())
+    }
+}
+
 #[account]
 pub struct DonationState {
+    // Only burns of BONK are counted, each mint's burns are tracked by its VaultMintState
     pub bonk_burned: u64,
     pub sol_donated: u64,
     pub sol_matched: u64,
+    // Lamports committed to pending matches
+    pub reserved_lamports: u64,
+    // Lamports received from swaps beyond what the vault paid for
+    pub sol_surplus: u64,
+    // Matches holding a reservation, each of which may pay a keeper reward
+    pub pending_matches: u64,
+    // Numbers every match, so each has a match record address of its own
+    pub match_nonce: u64,
 }
 
 impl Space for DonationState {
-    const INIT_SPACE: usize = 8 + 8 + 8 + 8;
+    const INIT_SPACE: usize = 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8;
+}
+
+impl DonationState {
+    // Reserve a pending match's target, within the capacity if it's bounded
+    pub fn reserve(&mut self, target_lamports: u64, capacity: Option<u128>) -> Result<()> {
+        let reserved_lamports = self.reserved_lamports.checked_add(target_lamports).ok_or(BonkPawsError::Overflow)?;
+        if let Some(capacity) = capacity {
+            require_gte!(capacity, reserved_lamports as u128, BonkPawsError::InsufficientVaultCapacity);
+        }
+
+        self.reserved_lamports = reserved_lamports;
+        self.pending_matches = self.pending_matches.checked_add(1).ok_or(BonkPawsError::Overflow)?;
+
+        Ok(())
+    }
+
+    // Release the reservations of matches that were finalized or never will be,
+    // matches created before reservations were tracked hold none
+    pub fn release(&mut self, target_lamports: u64, matches: u64) {
+        self.reserved_lamports = self.reserved_lamports.saturating_sub(target_lamports);
+        self.pending_matches = self.pending_matches.saturating_sub(matches);
+    }
+}
+
+// Running totals of a single vault mint
+#[account]
+pub struct VaultMintState {
+    pub mint: Pubkey,
+    pub amount_burned: u64,
+    pub bump: u8,
+}
+
+impl Space for VaultMintState {
+    const INIT_SPACE: usize = 8 + 32 + 8 + 1;
+}
+
+// Lamports sponsors have set aside to match donations in SOL, without a swap
+#[account]
+pub struct SolTreasury {
+    pub funded_lamports: u64,
+    pub matched_lamports: u64,
+    pub withdrawn_lamports: u64,
+    pub bump: u8,
+}
+
+impl Space for SolTreasury {
+    const INIT_SPACE: usize = 8 + 8 + 8 + 8 + 1;
+}
+
+impl SolTreasury {
+    // Lamports above the treasury's rent exempt minimum
+    pub fn available_lamports(treasury: &AccountInfo) -> Result<u64> {
+        let rent_exempt_lamports = Rent::get()?.minimum_balance(treasury.data_len());
+        Ok(treasury.lamports().saturating_sub(rent_exempt_lamports))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MatchStatus {
+    Pending,
+    Swapping,
+    Finalized,
 }
 
 #[account]
 pub struct MatchDonationState {
     pub id: u64,
+    // Lamports originally donated
     pub donation_amount: u64,
+    // Lamports to match, after applying the match ratio at donation time
+    pub target_lamports: u64,
     pub match_key: Pubkey,
     pub seed: u64,
+    // Seeds the match record, as donors may reuse a seed once a match is closed
+    pub nonce: u64,
+    // The campaign the target was reserved in, if any
+    pub campaign: Option<Pubkey>,
+    pub donor: Pubkey,
+    pub charity: Pubkey,
+    pub created_at: i64,
+    // Vault balance snapshotted by match_donation for cost comparison in finalization
+    pub pre_match_vault_balance: u64,
+    pub status: MatchStatus,
 }
 
 impl Space for MatchDonationState {
-    const INIT_SPACE: usize = 8 + 8 + 8 + 32 + 8;
+    const INIT_SPACE: usize = 8 + 8 + 8 + 8 + 32 + 8 + 8 + (1 + 32) + 32 + 32 + 8 + 8 + 1;
+}
+
+impl MatchDonationState {
+    // Pending -> Swapping -> Finalized
+    pub fn transition(&mut self, status: MatchStatus) -> Result<()> {
+        match (self.status, status) {
+            (MatchStatus::Pending, MatchStatus::Swapping) | (MatchStatus::Swapping, MatchStatus::Finalized) => {
+                self.status = status;
+                Ok(())
+            },
+            _ => Err(BonkPawsError::InvalidMatchStatus.into()),
+        }
+    }
+
+    // Release the reservations held by a match that will never be finalized
+    pub fn release(
+        &self,
+        donation_state: &mut DonationState,
+        campaign: Option<&AccountInfo>,
+        donor_match_cap: &AccountInfo,
+        charity_match_cap: &AccountInfo,
+    ) -> Result<()> {
+        require!(self.status == MatchStatus::Pending, BonkPawsError::InvalidMatchStatus);
+
+        donation_state.release(self.target_lamports, 1);
+
+        // Its sponsor may have closed the campaign since
+        if let Some(key) = self.campaign {
+            let campaign = campaign.ok_or(BonkPawsError::InvalidCampaign)?;
+            require_keys_eq!(campaign.key(), key, BonkPawsError::InvalidCampaign);
+            update_if_initialized(campaign, |campaign: &mut Campaign| campaign.release(self.target_lamports))?;
+        }
+
+        // Caps are only created once enabled
+        update_if_initialized(donor_match_cap, |cap: &mut DonorMatchCap| cap.release(self.target_lamports, self.created_at))?;
+        update_if_initialized(charity_match_cap, |cap: &mut CharityMatchCap| cap.release(self.target_lamports, self.created_at))?;
+
+        Ok(())
+    }
+}
+
+// Update one of our accounts in place, skipping it if it doesn't exist
+fn update_if_initialized<T: AccountSerialize + AccountDeserialize>(account: &AccountInfo, update: impl FnOnce(&mut T)) -> Result<()> {
+    if account.owner.eq(&crate::ID) {
+        let mut data = account.try_borrow_mut_data()?;
+        let mut state = T::try_deserialize(&mut &data[..])?;
+        update(&mut state);
+        state.try_serialize(&mut &mut data[..])?;
+    }
+
+    Ok(())
+}
+
+// How a match was paid for
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MatchSource {
+    // Swapped from a vault mint, with the execution priced
+    Swap,
+    // Paid in SOL from the treasury, spending and pricing nothing
+    Treasury,
+}
+
+#[account]
+pub struct MatchRecord {
+    pub id: u64,
+    pub seed: u64,
+    pub nonce: u64,
+    pub donor: Pubkey,
+    pub match_key: Pubkey,
+    pub campaign: Option<Pubkey>,
+    pub donation_amount: u64,
+    pub lamports_matched: u64,
+    pub source: MatchSource,
+    // The vault mint swapped from, spent and burned amounts are in its base units
+    pub mint: Pubkey,
+    pub amount_spent: u64,
+    pub amount_burned: u64,
+    // Prices are lamports per base unit of the mint, scaled by PRICE_PRECISION,
+    // and only set for swapped matches
+    pub effective_price: u64,
+    pub quoted_price: u64,
+    // Negative slippage is a price improvement over the quote
+    pub slippage_bps: i64,
+    pub status: MatchStatus,
+    pub timestamp: i64,
+}
+
+impl Space for MatchRecord {
+    const INIT_SPACE: usize = 8 + 8 + 8 + 8 + 32 + 32 + (1 + 32) + 8 + 8 + 1 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 8;
+}
+
+impl MatchRecord {
+    // The swap is priced by what it returned, including any surplus over the match
+    pub fn new(
+        match_donation_state: &MatchDonationState,
+        lamports_matched: u64,
+        lamports_received: u64,
+        mint: Pubkey,
+        amount_spent: u64,
+        amount_burned: u64,
+        quoted_price: u64,
+    ) -> Result<Self> {
+        let effective_price = Self::price(lamports_received, amount_spent)?;
+
+        Ok(Self {
+            id: match_donation_state.id,
+            seed: match_donation_state.seed,
+            nonce: match_donation_state.nonce,
+            donor: match_donation_state.donor,
+            match_key: match_donation_state.match_key,
+            campaign: match_donation_state.campaign,
+            donation_amount: match_donation_state.donation_amount,
+            lamports_matched,
+            source: MatchSource::Swap,
+            mint,
+            amount_spent,
+            amount_burned,
+            effective_price,
+            quoted_price,
+            slippage_bps: Self::slippage_bps(effective_price, quoted_price)?,
+            status: match_donation_state.status,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    pub fn price(lamports: u64, amount: u64) -> Result<u64> {
+        let price = (lamports as u128)
+            .checked_mul(PRICE_PRECISION).ok_or(BonkPawsError::Overflow)?
+            .checked_div(amount as u128).ok_or(BonkPawsError::Overflow)?;
+
+        u64::try_from(price).map_err(|_| BonkPawsError::Overflow.into())
+    }
+
+    pub fn slippage_bps(effective_price: u64, quoted_price: u64) -> Result<i64> {
+        let slippage_bps = (quoted_price as i128)
+            .checked_sub(effective_price as i128).ok_or(BonkPawsError::Overflow)?
+            .checked_mul(10000).ok_or(BonkPawsError::Overflow)?
+            .checked_div(quoted_price as i128).ok_or(BonkPawsError::Overflow)?;
+
+        i64::try_from(slippage_bps).map_err(|_| BonkPawsError::Overflow.into())
+    }
+}
+
+#[account]
+pub struct DonorMatchCap {
+    pub window_start: i64,
+    pub matched_lamports: u64,
+}
+
+impl Space for DonorMatchCap {
+    const INIT_SPACE: usize = 8 + 8 + 8;
+}
+
+impl DonorMatchCap {
+    // The window restarts with the first match after the previous one has elapsed
+    pub fn record(&mut self, cap: u64, window_seconds: i64, lamports: u64, timestamp: i64) -> Result<()> {
+        if timestamp >= self.window_start.checked_add(window_seconds).ok_or(BonkPawsError::Overflow)? {
+            self.window_start = timestamp;
+            self.matched_lamports = 0;
+        }
+
+        let matched_lamports = self.matched_lamports.checked_add(lamports).ok_or(BonkPawsError::Overflow)?;
+        require_gte!(cap, matched_lamports, BonkPawsError::DonorMatchCapExceeded);
+        self.matched_lamports = matched_lamports;
+
+        Ok(())
+    }
+
+    // Give back a match that was recorded in the current window
+    pub fn release(&mut self, lamports: u64, created_at: i64) {
+        if created_at >= self.window_start {
+            self.matched_lamports = self.matched_lamports.saturating_sub(lamports);
+        }
+    }
+}
+
+#[account]
+pub struct CharityMatchCap {
+    pub day: i64,
+    pub matched_lamports: u64,
+}
+
+impl Space for CharityMatchCap {
+    const INIT_SPACE: usize = 8 + 8 + 8;
+}
+
+impl CharityMatchCap {
+    pub fn record(&mut self, cap: u64, lamports: u64, timestamp: i64) -> Result<()> {
+        let day = timestamp.checked_div(SECONDS_PER_DAY).ok_or(BonkPawsError::Overflow)?;
+        if day != self.day {
+            self.day = day;
+            self.matched_lamports = 0;
+        }
+
+        let matched_lamports = self.matched_lamports.checked_add(lamports).ok_or(BonkPawsError::Overflow)?;
+        require_gte!(cap, matched_lamports, BonkPawsError::CharityMatchCapExceeded);
+        self.matched_lamports = matched_lamports;
+
+        Ok(())
+    }
+
+    // Give back a match that was recorded on the current day
+    pub fn release(&mut self, lamports: u64, created_at: i64) {
+        if created_at.checked_div(SECONDS_PER_DAY) == Some(self.day) {
+            self.matched_lamports = self.matched_lamports.saturating_sub(lamports);
+        }
+    }
 }
 
 #[account]
@@ -33,4 +603,160 @@ pub struct DonationHistory {
 
 impl Space for DonationHistory {
     const INIT_SPACE: usize = 8 + 32 + 8 + 8 + 8;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::{entrypoint::SUCCESS, program_stubs::{set_syscall_stubs, SyscallStubs}};
+
+    const SLOT: u64 = 1_000;
+
+    // Clock::get() has no runtime to read from off-chain
+    struct FixedSlot;
+
+    impl SyscallStubs for FixedSlot {
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe { *(var_addr as *mut Clock) = Clock { slot: SLOT, ..Clock::default() } };
+            SUCCESS
+        }
+    }
+
+    fn oracle_policy() -> OraclePolicy {
+        set_syscall_stubs(Box::new(FixedSlot));
+        OraclePolicy { enabled: true, max_deviation_bps: 100, max_staleness_slots: 10 }
+    }
+
+    // 0.025 SOL per token
+    fn price_account(status: u32, pub_slot: u64) -> PriceAccount {
+        PriceAccount { expo: -8, price: 2_500_000, conf: 0, status, pub_slot }
+    }
+
+    #[test]
+    fn oracle_value_scales_down_negative_exponents() {
+        // 10 tokens with 5 decimals are worth 0.25 SOL
+        assert_eq!(oracle_policy().value(&price_account(STATUS_TRADING, SLOT), 1_000_000, 5, 9).unwrap(), 250_000_000);
+    }
+
+    #[test]
+    fn oracle_value_scales_up_positive_exponents() {
+        let price_account = PriceAccount { expo: -2, price: 250, conf: 0, status: STATUS_TRADING, pub_slot: SLOT };
+
+        // 4 whole tokens at 2.5 SOL each
+        assert_eq!(oracle_policy().value(&price_account, 4, 0, 9).unwrap(), 10_000_000_000);
+    }
+
+    #[test]
+    fn oracle_value_requires_trading_status() {
+        assert_eq!(
+            oracle_policy().value(&price_account(0, SLOT), 1_000_000, 5, 9).err(),
+            Some(BonkPawsError::InvalidOracle.into())
+        );
+    }
+
+    #[test]
+    fn oracle_value_requires_positive_price() {
+        let price_account = PriceAccount { price: 0, ..price_account(STATUS_TRADING, SLOT) };
+
+        assert_eq!(
+            oracle_policy().value(&price_account, 1_000_000, 5, 9).err(),
+            Some(BonkPawsError::InvalidOracle.into())
+        );
+    }
+
+    #[test]
+    fn oracle_value_rejects_stale_prices() {
+        let oracle_policy = oracle_policy();
+
+        assert!(oracle_policy.value(&price_account(STATUS_TRADING, SLOT - 10), 1_000_000, 5, 9).is_ok());
+        assert_eq!(
+            oracle_policy.value(&price_account(STATUS_TRADING, SLOT - 11), 1_000_000, 5, 9).err(),
+            Some(BonkPawsError::StaleOracle.into())
+        );
+    }
+
+    #[test]
+    fn oracle_validate_allows_deviation_within_band() {
+        let oracle_policy = oracle_policy();
+        let price_account = price_account(STATUS_TRADING, SLOT);
+
+        for out_amount in [250_000_000, 252_500_000, 247_500_000] {
+            assert!(oracle_policy.validate(&price_account, 1_000_000, 5, out_amount, 9).is_ok());
+        }
+    }
+
+    #[test]
+    fn oracle_validate_rejects_deviation_above_band() {
+        assert_eq!(
+            oracle_policy().validate(&price_account(STATUS_TRADING, SLOT), 1_000_000, 5, 252_525_001, 9).err(),
+            Some(BonkPawsError::OraclePriceDeviation.into())
+        );
+    }
+
+    #[test]
+    fn oracle_validate_rejects_deviation_below_band() {
+        assert_eq!(
+            oracle_policy().validate(&price_account(STATUS_TRADING, SLOT), 1_000_000, 5, 247_474_999, 9).err(),
+            Some(BonkPawsError::OraclePriceDeviation.into())
+        );
+    }
+
+    #[test]
+    fn donor_match_cap_releases_matches_in_the_current_window() {
+        let mut cap = DonorMatchCap { window_start: 0, matched_lamports: 0 };
+        cap.record(100, 1_000, 60, 10).unwrap();
+        cap.release(60, 10);
+        assert_eq!(cap.matched_lamports, 0);
+
+        // A match from a previous window no longer counts against the cap
+        cap.record(100, 1_000, 60, 20).unwrap();
+        cap.record(100, 1_000, 80, 2_000).unwrap();
+        cap.release(60, 20);
+        assert_eq!(cap.matched_lamports, 80);
+    }
+
+    #[test]
+    fn charity_match_cap_releases_matches_of_the_current_day() {
+        let mut cap = CharityMatchCap { day: 0, matched_lamports: 0 };
+        cap.record(100, 60, SECONDS_PER_DAY + 10).unwrap();
+        cap.release(60, SECONDS_PER_DAY + 20);
+        assert_eq!(cap.matched_lamports, 0);
+
+        cap.record(100, 80, 2 * SECONDS_PER_DAY).unwrap();
+        cap.release(80, SECONDS_PER_DAY + 10);
+        assert_eq!(cap.matched_lamports, 80);
+    }
+
+    fn donation_state() -> DonationState {
+        DonationState {
+            bonk_burned: 0,
+            sol_donated: 0,
+            sol_matched: 0,
+            reserved_lamports: 0,
+            sol_surplus: 0,
+            pending_matches: 0,
+            match_nonce: 0,
+        }
+    }
+
+    #[test]
+    fn reservations_are_unbounded_without_a_capacity() {
+        let mut donation_state = donation_state();
+        donation_state.reserve(u64::MAX / 2, None).unwrap();
+        donation_state.reserve(u64::MAX / 2, None).unwrap();
+        assert_eq!(donation_state.pending_matches, 2);
+    }
+
+    #[test]
+    fn reservations_are_bounded_by_the_capacity() {
+        let mut donation_state = donation_state();
+        donation_state.reserve(60, Some(100)).unwrap();
+        donation_state.reserve(40, Some(100)).unwrap();
+        assert_eq!(donation_state.reserve(1, Some(100)).err(), Some(BonkPawsError::InsufficientVaultCapacity.into()));
+        assert_eq!((donation_state.reserved_lamports, donation_state.pending_matches), (100, 2));
+
+        donation_state.release(60, 1);
+        donation_state.reserve(60, Some(100)).unwrap();
+        assert_eq!((donation_state.reserved_lamports, donation_state.pending_matches), (100, 2));
+    }
+}
@@ -0,0 +1,210 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{transfer, Mint, Token, TokenAccount, Transfer},
+};
+
+use crate::{errors::PoolError, program::ConstantProduct, state::Pool};
+
+#[derive(Accounts)]
+pub struct InitializePool<'info> {
+    #[account(mut)]
+    admin: Signer<'info>,
+    #[account(constraint = mint_a.key() < mint_b.key() @ PoolError::InvalidPoolMints)]
+    mint_a: Account<'info, Mint>,
+    mint_b: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"pool", mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+        space = Pool::INIT_SPACE
+    )]
+    pool: Account<'info, Pool>,
+    #[account(
+        init,
+        payer = admin,
+        associated_token::mint = mint_a,
+        associated_token::authority = pool,
+    )]
+    vault_a: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = admin,
+        associated_token::mint = mint_b,
+        associated_token::authority = pool,
+    )]
+    vault_b: Account<'info, TokenAccount>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    program: Program<'info, ConstantProduct>,
+    // Only the upgrade authority may create pools
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ PoolError::Unauthorized)]
+    program_data: Account<'info, ProgramData>,
+    associated_token_program: Program<'info, AssociatedToken>,
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> InitializePool<'info> {
+    pub fn initialize_pool(&mut self, fee_bps: u16, bumps: InitializePoolBumps) -> Result<()> {
+        require_gt!(10000, fee_bps, PoolError::InvalidFee);
+
+        self.pool.set_inner(
+            Pool {
+                admin: self.admin.key(),
+                mint_a: self.mint_a.key(),
+                mint_b: self.mint_b.key(),
+                fee_bps,
+                bump: bumps.pool,
+            }
+        );
+
+        Ok(())
+    }
+}
+
+/*
+
+    Deposit
+
+    Liquidity is donated to the pool without minting any shares, which is
+    all a local test or fallback venue that we fund ourselves needs. Only
+    the pool's admin may deposit or withdraw, so nobody else can move its
+    price without paying the fee.
+
+*/
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    depositor: Signer<'info>,
+    #[account(
+        has_one = mint_a,
+        has_one = mint_b,
+        constraint = pool.is_admin(&depositor.key()) @ PoolError::Unauthorized,
+        seeds = [b"pool", mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump = pool.bump,
+    )]
+    pool: Account<'info, Pool>,
+    mint_a: Account<'info, Mint>,
+    mint_b: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = pool,
+    )]
+    vault_a: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = pool,
+    )]
+    vault_b: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_a,
+        token::authority = depositor,
+    )]
+    depositor_a: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_b,
+        token::authority = depositor,
+    )]
+    depositor_b: Account<'info, TokenAccount>,
+    token_program: Program<'info, Token>,
+}
+
+impl<'info> Deposit<'info> {
+    pub fn deposit(&mut self, amount_a: u64, amount_b: u64) -> Result<()> {
+        for (from, to, amount) in [
+            (&self.depositor_a, &self.vault_a, amount_a),
+            (&self.depositor_b, &self.vault_b, amount_b),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+
+            let transfer_accounts = Transfer {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                authority: self.depositor.to_account_info(),
+            };
+            let transfer_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+
+            transfer(transfer_ctx, amount)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    admin: Signer<'info>,
+    #[account(
+        has_one = mint_a,
+        has_one = mint_b,
+        constraint = pool.is_admin(&admin.key()) @ PoolError::Unauthorized,
+        seeds = [b"pool", mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump = pool.bump,
+    )]
+    pool: Account<'info, Pool>,
+    mint_a: Account<'info, Mint>,
+    mint_b: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = pool,
+    )]
+    vault_a: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = pool,
+    )]
+    vault_b: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_a,
+    )]
+    admin_a: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_b,
+    )]
+    admin_b: Account<'info, TokenAccount>,
+    token_program: Program<'info, Token>,
+}
+
+impl<'info> Withdraw<'info> {
+    pub fn withdraw(&mut self, amount_a: u64, amount_b: u64) -> Result<()> {
+        let seeds = &[
+            b"pool".as_ref(),
+            self.pool.mint_a.as_ref(),
+            self.pool.mint_b.as_ref(),
+            &[self.pool.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        for (from, to, amount) in [
+            (&self.vault_a, &self.admin_a, amount_a),
+            (&self.vault_b, &self.admin_b, amount_b),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+            Pool::withdraw(from.amount, amount)?;
+
+            let transfer_accounts = Transfer {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                authority: self.pool.to_account_info(),
+            };
+            let transfer_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_accounts, signer_seeds);
+
+            transfer(transfer_ctx, amount)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+
+use crate::{errors::PoolError, state::Pool};
+
+/*
+
+    Swap
+
+    Swaps mirror Jupiter's interface, taking the quoted amount and the
+    slippage tolerance rather than a bare threshold, so that callers can
+    inspect a swap instruction the same way across venues. The source 
+    tokens may be spent by a delegate of the source token account.
+
+*/
+#[derive(Accounts)]
+pub struct Swap<'info> {
+    #[account(
+        constraint = pool.has_mint(&source_mint.key()) @ PoolError::InvalidMint,
+        constraint = pool.has_mint(&destination_mint.key()) @ PoolError::InvalidMint,
+        seeds = [b"pool", pool.mint_a.as_ref(), pool.mint_b.as_ref()],
+        bump = pool.bump,
+    )]
+    pool: Account<'info, Pool>,
+    user_transfer_authority: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = source_mint,
+    )]
+    source_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = destination_mint,
+    )]
+    destination_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = source_mint,
+        associated_token::authority = pool,
+    )]
+    pool_source_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = destination_mint,
+        associated_token::authority = pool,
+    )]
+    pool_destination_vault: Account<'info, TokenAccount>,
+    #[account(constraint = source_mint.key() != destination_mint.key() @ PoolError::InvalidMint)]
+    source_mint: Account<'info, Mint>,
+    destination_mint: Account<'info, Mint>,
+    token_program: Program<'info, Token>,
+}
+
+impl<'info> Swap<'info> {
+    pub fn swap_exact_in(&mut self, amount_in: u64, quoted_out_amount: u64, slippage_bps: u16) -> Result<()> {
+        require_gt!(amount_in, 0, PoolError::InvalidAmount);
+        require_gte!(10000, slippage_bps, PoolError::InvalidSlippage);
+
+        let min_out_amount = (quoted_out_amount as u128)
+            .checked_mul(10000u128 - slippage_bps as u128).ok_or(PoolError::Overflow)?
+            .checked_div(10000).ok_or(PoolError::Overflow)?;
+        let amount_out = self.pool.amount_out(self.pool_source_vault.amount, self.pool_destination_vault.amount, amount_in)?;
+        require_gte!(amount_out as u128, min_out_amount, PoolError::SlippageExceeded);
+
+        self.transfer(amount_in, amount_out)
+    }
+
+    pub fn swap_exact_out(&mut self, amount_out: u64, quoted_in_amount: u64, slippage_bps: u16) -> Result<()> {
+        require_gt!(amount_out, 0, PoolError::InvalidAmount);
+        require_gte!(10000, slippage_bps, PoolError::InvalidSlippage);
+
+        let max_in_amount = (quoted_in_amount as u128)
+            .checked_mul(10000u128 + slippage_bps as u128).ok_or(PoolError::Overflow)?
+            .checked_div(10000).ok_or(PoolError::Overflow)?;
+        let amount_in = self.pool.amount_in(self.pool_source_vault.amount, self.pool_destination_vault.amount, amount_out)?;
+        require_gte!(max_in_amount, amount_in as u128, PoolError::SlippageExceeded);
+
+        self.transfer(amount_in, amount_out)
+    }
+
+    fn transfer(&self, amount_in: u64, amount_out: u64) -> Result<()> {
+        let transfer_accounts = Transfer {
+            from: self.source_token_account.to_account_info(),
+            to: self.pool_source_vault.to_account_info(),
+            authority: self.user_transfer_authority.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+
+        transfer(transfer_ctx, amount_in)?;
+
+        let seeds = &[
+            b"pool".as_ref(),
+            self.pool.mint_a.as_ref(),
+            self.pool.mint_b.as_ref(),
+            &[self.pool.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let transfer_accounts = Transfer {
+            from: self.pool_destination_vault.to_account_info(),
+            to: self.destination_token_account.to_account_info(),
+            authority: self.pool.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_accounts, signer_seeds);
+
+        transfer(transfer_ctx, amount_out)
+    }
+}
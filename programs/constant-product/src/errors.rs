@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum PoolError {
+    #[msg("Overflow")]
+    Overflow,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Invalid fee")]
+    InvalidFee,
+    #[msg("Invalid slippage")]
+    InvalidSlippage,
+    #[msg("Pool mints must be distinct and sorted")]
+    InvalidPoolMints,
+    #[msg("Mint is not in the pool")]
+    InvalidMint,
+    #[msg("Not enough liquidity")]
+    InsufficientLiquidity,
+    #[msg("Slippage exceeded")]
+    SlippageExceeded,
+    #[msg("Only the pool admin may do this")]
+    Unauthorized,
+}
@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+pub mod contexts;
+pub mod errors;
+pub mod state;
+
+use contexts::*;
+
+declare_id!("8BCsX7s7WzLWeESvgou13YAZ689oTLRhLxrrBh5jDb1o");
+
+#[program]
+pub mod constant_product {
+    use super::*;
+
+    pub fn initialize_pool(ctx: Context<InitializePool>, fee_bps: u16) -> Result<()> {
+        ctx.accounts.initialize_pool(fee_bps, ctx.bumps)
+    }
+
+    pub fn deposit(ctx: Context<Deposit>, amount_a: u64, amount_b: u64) -> Result<()> {
+        ctx.accounts.deposit(amount_a, amount_b)
+    }
+
+    pub fn withdraw(ctx: Context<Withdraw>, amount_a: u64, amount_b: u64) -> Result<()> {
+        ctx.accounts.withdraw(amount_a, amount_b)
+    }
+
+    pub fn swap_exact_in(ctx: Context<Swap>, amount_in: u64, quoted_out_amount: u64, slippage_bps: u16) -> Result<()> {
+        ctx.accounts.swap_exact_in(amount_in, quoted_out_amount, slippage_bps)
+    }
+
+    pub fn swap_exact_out(ctx: Context<Swap>, amount_out: u64, quoted_in_amount: u64, slippage_bps: u16) -> Result<()> {
+        ctx.accounts.swap_exact_out(amount_out, quoted_in_amount, slippage_bps)
+    }
+}
@@ -0,0 +1,121 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::PoolError;
+
+#[account]
+pub struct Pool {
+    pub admin: Pubkey,
+    // Sorted, so each pair of mints has a single pool
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub fee_bps: u16,
+    pub bump: u8,
+}
+
+impl Space for Pool {
+    const INIT_SPACE: usize = 8 + 32 + 32 + 32 + 2 + 1;
+}
+
+impl Pool {
+    pub fn has_mint(&self, mint: &Pubkey) -> bool {
+        self.mint_a.eq(mint) || self.mint_b.eq(mint)
+    }
+
+    // Only the admin may deposit or withdraw liquidity
+    pub fn is_admin(&self, key: &Pubkey) -> bool {
+        self.admin.eq(key)
+    }
+
+    // The reserve left after a withdrawal, which may empty the pool
+    pub fn withdraw(reserve: u64, amount: u64) -> Result<u64> {
+        reserve.checked_sub(amount).ok_or(PoolError::InsufficientLiquidity.into())
+    }
+
+    // Tokens out for an exact amount in, after the fee
+    pub fn amount_out(&self, reserve_in: u64, reserve_out: u64, amount_in: u64) -> Result<u64> {
+        let amount_in = (amount_in as u128)
+            .checked_mul(10000u128.checked_sub(self.fee_bps as u128).ok_or(PoolError::Overflow)?).ok_or(PoolError::Overflow)?
+            .checked_div(10000).ok_or(PoolError::Overflow)?;
+        let amount_out = (reserve_out as u128)
+            .checked_mul(amount_in).ok_or(PoolError::Overflow)?
+            .checked_div((reserve_in as u128).checked_add(amount_in).ok_or(PoolError::Overflow)?).ok_or(PoolError::Overflow)?;
+
+        u64::try_from(amount_out).map_err(|_| PoolError::Overflow.into())
+    }
+
+    // Tokens in for an exact amount out, including the fee and rounded up
+    pub fn amount_in(&self, reserve_in: u64, reserve_out: u64, amount_out: u64) -> Result<u64> {
+        require_gt!(reserve_out, amount_out, PoolError::InsufficientLiquidity);
+
+        let amount_in = (reserve_in as u128)
+            .checked_mul(amount_out as u128).ok_or(PoolError::Overflow)?
+            .checked_div((reserve_out - amount_out) as u128).ok_or(PoolError::Overflow)?
+            .checked_add(1).ok_or(PoolError::Overflow)?;
+        let fee_complement = 10000u128.checked_sub(self.fee_bps as u128).ok_or(PoolError::Overflow)?;
+        let amount_in = amount_in
+            .checked_mul(10000).ok_or(PoolError::Overflow)?
+            .checked_add(fee_complement - 1).ok_or(PoolError::Overflow)?
+            .checked_div(fee_complement).ok_or(PoolError::Overflow)?;
+
+        u64::try_from(amount_in).map_err(|_| PoolError::Overflow.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(fee_bps: u16) -> Pool {
+        Pool { admin: Pubkey::new_unique(), mint_a: Pubkey::new_unique(), mint_b: Pubkey::new_unique(), fee_bps, bump: 255 }
+    }
+
+    #[test]
+    fn only_the_admin_moves_liquidity() {
+        let pool = pool(30);
+        assert!(pool.is_admin(&pool.admin));
+        assert!(!pool.is_admin(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn withdrawals_are_bounded_by_the_reserve() {
+        assert_eq!(Pool::withdraw(1_000, 400).unwrap(), 600);
+        assert_eq!(Pool::withdraw(1_000, 1_000).unwrap(), 0);
+        assert_eq!(Pool::withdraw(1_000, 1_001).err(), Some(PoolError::InsufficientLiquidity.into()));
+
+        // An emptied side can no longer be bought from
+        assert_eq!(pool(30).amount_in(1_000, Pool::withdraw(1_000, 1_000).unwrap(), 1).err(), Some(PoolError::InsufficientLiquidity.into()));
+    }
+
+    #[test]
+    fn amount_out_rounds_down_after_the_fee() {
+        // 997 in after the fee buys 996.006...
+        assert_eq!(pool(30).amount_out(1_000_000, 1_000_000, 1_000).unwrap(), 996);
+        // Too small to buy a whole token
+        assert_eq!(pool(0).amount_out(100, 1, 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn amount_in_rounds_up_including_the_fee() {
+        assert_eq!(pool(30).amount_in(1_000_000, 1_000_000, 996).unwrap(), 1_000);
+        assert_eq!(pool(0).amount_in(100, 1_000, 10).unwrap(), 2);
+    }
+
+    #[test]
+    fn amount_in_always_buys_the_amount_out() {
+        for fee_bps in [0, 30, 9_999] {
+            let pool = pool(fee_bps);
+            for (reserve_in, reserve_out) in [(7, 11), (1_000, 1_000), (123_457, 999_983)] {
+                for amount_out in 1..reserve_out.min(300) {
+                    let amount_in = pool.amount_in(reserve_in, reserve_out, amount_out).unwrap();
+                    assert!(pool.amount_out(reserve_in, reserve_out, amount_in).unwrap() >= amount_out);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn amount_in_requires_liquidity() {
+        assert_eq!(pool(30).amount_in(1_000, 1_000, 1_000).err(), Some(PoolError::InsufficientLiquidity.into()));
+        assert_eq!(pool(30).amount_in(1_000, 1_000, 1_001).err(), Some(PoolError::InsufficientLiquidity.into()));
+    }
+}
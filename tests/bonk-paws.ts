@@ -1,22 +1,37 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program, BN } from "@coral-xyz/anchor";
 import { BonkPaws } from "../target/types/bonk_paws";
-import { ComputeBudgetProgram, Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram, Transaction, Ed25519Program } from "@solana/web3.js";
-import { ASSOCIATED_PROGRAM_ID, TOKEN_PROGRAM_ID, associatedAddress } from "@coral-xyz/anchor/dist/cjs/utils/token";
-import { associated } from "@coral-xyz/anchor/dist/cjs/utils/pubkey";
-import { getOrCreateAssociatedTokenAccount } from "@solana/spl-token";
+import { ConstantProduct } from "../target/types/constant_product";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram, SYSVAR_INSTRUCTIONS_PUBKEY, Transaction, TransactionInstruction } from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  NATIVE_MINT,
+  TOKEN_PROGRAM_ID,
+  createMint,
+  createSyncNativeInstruction,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+import { generateKeyPairSync, sign, KeyObject } from "crypto";
 
 describe("bonk-paws", () => {
   // Configure the client to use the local cluster.
   anchor.setProvider(anchor.AnchorProvider.env());
 
-  const signer = Keypair.generate();
-  const charity = Keypair.generate();
-  let donorBonk: PublicKey;
-  let poolBonk: PublicKey;
-  let poolWsol: PublicKey;
+  const provider = anchor.getProvider() as anchor.AnchorProvider;
+  const connection = provider.connection;
+  const program = anchor.workspace.BonkPaws as Program<BonkPaws>;
+  const poolProgram = anchor.workspace.ConstantProduct as Program<ConstantProduct>;
 
-  const connection = anchor.getProvider().connection;
+  const admin = provider.wallet.publicKey;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+  const donor = Keypair.generate();
+  const charity = Keypair.generate();
+  const matchKey = Keypair.generate();
+  let mint: PublicKey;
 
   const confirm = async (signature: string): Promise<string> => {
     const block = await connection.getLatestBlockhash();
@@ -26,50 +41,463 @@ describe("bonk-paws", () => {
     })
     return signature
   }
-  
+
   const log = async(signature: string): Promise<string> => {
     console.log(`Your transaction signature: https://explorer.solana.com/transaction/${signature}?cluster=custom&customUrl=${connection.rpcEndpoint}`);
     return signature;
   }
 
-  const bonk = new PublicKey('DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263');
-  const wsol = new PublicKey('So11111111111111111111111111111111111111112');
+  const pda = (...seeds: Buffer[]) => PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+  const u64 = (value: number) => new BN(value).toArrayLike(Buffer, "le", 8);
 
-  const program = anchor.workspace.BonkPaws as Program<BonkPaws>;
+  const config = pda(Buffer.from("config"));
+  const donationState = pda(Buffer.from("donation_state"));
+  const programDataOf = (programId: PublicKey) => PublicKey.findProgramAddressSync(
+    [programId.toBuffer()],
+    new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+  )[0];
+  const programData = programDataOf(program.programId);
+
+  /*
+
+    secp256r1 Approvals
+
+    Our approval service signs with a P-256 key, verified by the secp256r1
+    precompile. The program only accepts a single signature whose public
+    key, signature and message are all read from the precompile itself.
+
+  */
+
+  const SECP256R1_PROGRAM_ID = new PublicKey("Secp256r1SigVerify1111111111111111111111111");
+  const SECP256R1_HEADER = Buffer.from([0x01, 0x00, 0x31, 0x00, 0xff, 0xff, 0x10, 0x00, 0xff, 0xff, 0x71, 0x00, 0x48, 0x00, 0xff, 0xff]);
+  const P256_ORDER = new BN("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551", 16);
+
+  const approvalKey = generateKeyPairSync("ec", { namedCurve: "prime256v1" });
+
+  const compressedPublicKey = (publicKey: KeyObject): Buffer => {
+    const { x, y } = publicKey.export({ format: "jwk" });
+    const yBytes = Buffer.from(y, "base64url");
+    return Buffer.concat([Buffer.from([yBytes[31] % 2 === 0 ? 0x02 : 0x03]), Buffer.from(x, "base64url")]);
+  }
+
+  const approvalMessage = (id: number, charity: PublicKey, matchKey: PublicKey): Buffer =>
+    Buffer.concat([u64(id), charity.toBuffer(), matchKey.toBuffer()]);
+
+  const secp256r1Ix = (message: Buffer, key = approvalKey): TransactionInstruction => {
+    // The precompile only accepts low S signatures
+    const signature = sign("sha256", message, { key: key.privateKey, dsaEncoding: "ieee-p1363" });
+    let s = new BN(signature.subarray(32));
+    if (s.gt(P256_ORDER.shrn(1))) {
+      s = P256_ORDER.sub(s);
+    }
+    const lowS = s.toArrayLike(Buffer, "be", 32);
+
+    return new TransactionInstruction({
+      programId: SECP256R1_PROGRAM_ID,
+      keys: [],
+      data: Buffer.concat([SECP256R1_HEADER, compressedPublicKey(key.publicKey), signature.subarray(0, 32), lowS, message]),
+    });
+  }
+
+  const donate = (
+    seed: number,
+    lamports: number,
+    preInstructions: TransactionInstruction[],
+    { vault, oracle }: { vault: PublicKey | null, oracle: PublicKey | null } = { vault: null, oracle: null }
+  ) =>
+    program.methods
+      .donate(new BN(seed), new BN(lamports), null)
+      .accounts({
+        donor: donor.publicKey,
+        charity: charity.publicKey,
+        config,
+        charityConfig: pda(Buffer.from("charity"), charity.publicKey.toBuffer()),
+        campaign: null,
+        donationState,
+        mint,
+        vault,
+        oracle,
+        solTreasury: null,
+        matchDonationState: pda(Buffer.from("match_donation"), u64(seed)),
+        donorMatchCap: null,
+        charityMatchCap: null,
+        donationHistory: pda(Buffer.from("donation_history"), u64(seed), donor.publicKey.toBuffer()),
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+      })
+      .preInstructions(preInstructions)
+      .signers([donor])
+      .rpc();
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    const error = await promise.then(() => null, (e) => e);
+    expect(error?.error?.errorCode?.code).to.equal(code);
+  }
+
+  it("Airdrop", async () => {
+    await connection.requestAirdrop(donor.publicKey, LAMPORTS_PER_SOL * 10)
+    .then(confirm)
+    .then(log)
+  })
+
+  it("Initializes the config", async () => {
+    await program.methods
+      .initializeConfig({ ed25519: {} })
+      .accounts({
+        admin,
+        config,
+        program: program.programId,
+        programData,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc()
+      .then(confirm)
+      .then(log);
+
+    const account = await program.account.config.fetch(config);
+    expect(account.admin.toBase58()).to.equal(admin.toBase58());
+    expect(account.approvalScheme).to.deep.equal({ ed25519: {} });
+  })
+
+  it("Matches from a local vault mint", async () => {
+    mint = await createMint(connection, payer, admin, null, 6);
+
+    await program.methods
+      .setVaultMints([{ mint, burnBps: 0, keeperReward: new BN(0), priceAccount: PublicKey.default }])
+      .accounts({ admin, config })
+      .rpc()
+      .then(confirm)
+      .then(log);
+  })
+
+  it("Caps reservations without an oracle", async () => {
+    await program.methods
+      .setMaxReservedLamports(new BN(LAMPORTS_PER_SOL))
+      .accounts({ admin, config })
+      .rpc()
+      .then(confirm)
+      .then(log);
+
+    const account = await program.account.config.fetch(config);
+    expect(account.maxReservedLamports.toNumber()).to.equal(LAMPORTS_PER_SOL);
+  })
+
+  it("Accepts secp256r1 approvals once configured", async () => {
+    const authority = [...compressedPublicKey(approvalKey.publicKey)];
+
+    await program.methods
+      .setApprovalScheme({ secp256r1: { authority } })
+      .accounts({ admin, config })
+      .rpc()
+      .then(confirm)
+      .then(log);
+
+    const account = await program.account.config.fetch(config);
+    expect(account.approvalScheme).to.deep.equal({ secp256r1: { authority } });
+  })
+
+  it("Donates with a secp256r1 approval", async () => {
+    const seed = 1;
+    const lamports = LAMPORTS_PER_SOL / 10;
+    const message = approvalMessage(7, charity.publicKey, matchKey.publicKey);
+
+    await donate(seed, lamports, [secp256r1Ix(message)])
+      .then(confirm)
+      .then(log);
+
+    expect(await connection.getBalance(charity.publicKey)).to.equal(lamports);
+    const matchDonationState = await program.account.matchDonationState.fetch(pda(Buffer.from("match_donation"), u64(seed)));
+    expect(matchDonationState.id.toNumber()).to.equal(7);
+    expect(matchDonationState.matchKey.toBase58()).to.equal(matchKey.publicKey.toBase58());
+    expect(matchDonationState.targetLamports.toNumber()).to.equal(lamports);
+  })
+
+  it("Rejects a secp256r1 approval from another key", async () => {
+    const otherKey = generateKeyPairSync("ec", { namedCurve: "prime256v1" });
+    const message = approvalMessage(7, charity.publicKey, matchKey.publicKey);
+
+    await expectError(donate(2, LAMPORTS_PER_SOL / 10, [secp256r1Ix(message, otherKey)]), "SignatureAuthorityMismatch");
+  })
+
+  it("Rejects an ed25519 approval while secp256r1 is configured", async () => {
+    const message = approvalMessage(7, charity.publicKey, matchKey.publicKey);
+    const ed25519Ix = anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: Keypair.generate().secretKey,
+      message,
+    });
+
+    await expectError(donate(3, LAMPORTS_PER_SOL / 10, [ed25519Ix]), "ProgramMismatch");
+  })
+
+  /*
+
+    Constant Product Pool
+
+    A match swaps from the vault on a local pool rather than Jupiter. The
+    pool venue is only accepted for allow-listed pools and while quotes
+    are checked against the oracle, whose price account is loaded into the
+    validator from a fixture at 0.01 SOL per token.
+
+  */
+
+  const priceAccount = new PublicKey("4U8e1CtzVS2LtVxkY7P1GC9ua55HeRPk9T8vz5CWQX7H");
+  const keeper = Keypair.generate();
+  let pool: PublicKey;
+  let vault: PublicKey;
+
+  const sortedMints = (): [PublicKey, PublicKey] =>
+    Buffer.compare(mint.toBuffer(), NATIVE_MINT.toBuffer()) < 0 ? [mint, NATIVE_MINT] : [NATIVE_MINT, mint];
+
+  // Tokens in for an exact amount out, as the pool computes them
+  const poolAmountIn = (reserveIn: BN, reserveOut: BN, amountOut: BN, feeBps: number): BN => {
+    const feeComplement = new BN(10000 - feeBps);
+    const amountIn = reserveIn.mul(amountOut).div(reserveOut.sub(amountOut)).addn(1);
+    return amountIn.muln(10000).add(feeComplement).subn(1).div(feeComplement);
+  }
+
+  it("Creates a constant product pool", async () => {
+    const [mintA, mintB] = sortedMints();
+    pool = PublicKey.findProgramAddressSync([Buffer.from("pool"), mintA.toBuffer(), mintB.toBuffer()], poolProgram.programId)[0];
+
+    await poolProgram.methods
+      .initializePool(30)
+      .accounts({
+        admin,
+        mintA,
+        mintB,
+        pool,
+        vaultA: getAssociatedTokenAddressSync(mintA, pool, true),
+        vaultB: getAssociatedTokenAddressSync(mintB, pool, true),
+        program: poolProgram.programId,
+        programData: programDataOf(poolProgram.programId),
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc()
+      .then(confirm)
+      .then(log);
+
+    // 100,000 tokens against 1,000 SOL, at the oracle's price
+    const adminMint = await getOrCreateAssociatedTokenAccount(connection, payer, mint, admin);
+    await mintTo(connection, payer, mint, adminMint.address, payer, 100_000_000_000);
+    const adminWsol = await getOrCreateAssociatedTokenAccount(connection, payer, NATIVE_MINT, admin);
+    await provider.sendAndConfirm(new Transaction().add(
+      SystemProgram.transfer({ fromPubkey: admin, toPubkey: adminWsol.address, lamports: 1_000 * LAMPORTS_PER_SOL }),
+      createSyncNativeInstruction(adminWsol.address),
+    ));
+
+    const amounts = new Map([[mint.toBase58(), new BN(100_000_000_000)], [NATIVE_MINT.toBase58(), new BN(1_000 * LAMPORTS_PER_SOL)]]);
+    const depositor = new Map([[mint.toBase58(), adminMint.address], [NATIVE_MINT.toBase58(), adminWsol.address]]);
+
+    await poolProgram.methods
+      .deposit(amounts.get(mintA.toBase58()), amounts.get(mintB.toBase58()))
+      .accounts({
+        depositor: admin,
+        pool,
+        mintA,
+        mintB,
+        vaultA: getAssociatedTokenAddressSync(mintA, pool, true),
+        vaultB: getAssociatedTokenAddressSync(mintB, pool, true),
+        depositorA: depositor.get(mintA.toBase58()),
+        depositorB: depositor.get(mintB.toBase58()),
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc()
+      .then(confirm)
+      .then(log);
+  })
+
+  it("Allows the pool venue with the oracle", async () => {
+    await program.methods
+      .setVaultMints([{ mint, burnBps: 0, keeperReward: new BN(0), priceAccount }])
+      .accounts({ admin, config })
+      .rpc()
+      .then(confirm);
+
+    await program.methods
+      .setOraclePolicy({ enabled: true, maxDeviationBps: 500, maxStalenessSlots: new BN(10_000_000) })
+      .accounts({ admin, config })
+      .rpc()
+      .then(confirm);
+
+    await program.methods
+      .setRoutePolicy({ filter: { deny: {} }, amms: new BN(0), maxHops: 255, pools: [pool] })
+      .accounts({ admin, config })
+      .rpc()
+      .then(confirm);
+
+    // Third party keepers may match while the oracle is enabled
+    await program.methods
+      .setKeeperPolicy({ permissionless: true })
+      .accounts({ admin, config })
+      .rpc()
+      .then(confirm);
+
+    // 10,000 tokens in the vault, worth 100 SOL
+    vault = (await getOrCreateAssociatedTokenAccount(connection, payer, mint, donationState, true)).address;
+    await mintTo(connection, payer, mint, vault, payer, 10_000_000_000);
+
+    await connection.requestAirdrop(keeper.publicKey, LAMPORTS_PER_SOL)
+      .then(confirm);
+
+    const account = await program.account.config.fetch(config);
+    expect(account.routePolicy.pools.map((key) => key.toBase58())).to.deep.equal([pool.toBase58()]);
+  })
+
+  // A match, pool swap and finalize of the donation with the given seed
+  const poolMatch = async (seed: number) => {
+    const matchDonationState = pda(Buffer.from("match_donation"), u64(seed));
+    const { targetLamports, nonce } = await program.account.matchDonationState.fetch(matchDonationState);
+    const matchRecord = pda(Buffer.from("match_record"), nonce.toArrayLike(Buffer, "le", 8));
+    const keeperWsol = getAssociatedTokenAddressSync(NATIVE_MINT, keeper.publicKey);
+    const poolVault = getAssociatedTokenAddressSync(mint, pool, true);
+    const poolWsolVault = getAssociatedTokenAddressSync(NATIVE_MINT, pool, true);
+
+    // Quote the exact amount of tokens the target costs
+    const reserveIn = new BN((await getAccount(connection, poolVault)).amount.toString());
+    const reserveOut = new BN((await getAccount(connection, poolWsolVault)).amount.toString());
+    const quotedInAmount = poolAmountIn(reserveIn, reserveOut, targetLamports, 30);
+
+    const swapIx = await poolProgram.methods
+      .swapExactOut(targetLamports, quotedInAmount, 50)
+      .accounts({
+        pool,
+        userTransferAuthority: keeper.publicKey,
+        sourceTokenAccount: vault,
+        destinationTokenAccount: keeperWsol,
+        poolSourceVault: poolVault,
+        poolDestinationVault: poolWsolVault,
+        sourceMint: mint,
+        destinationMint: NATIVE_MINT,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction();
+
+    const finalizeIx = await program.methods
+      .finalizeDonation()
+      .accounts({
+        signer: keeper.publicKey,
+        matchKey: matchKey.publicKey,
+        donor: donor.publicKey,
+        treasury: null,
+        mint,
+        vault,
+        vaultMintState: pda(Buffer.from("vault_mint"), mint.toBuffer()),
+        keeperToken: null,
+        wsol: NATIVE_MINT,
+        signerWsol: keeperWsol,
+        wsolVault: null,
+        config,
+        donationState,
+        matchDonationState,
+        matchRecord,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+
+    const send = () => program.methods
+      .matchDonation()
+      .accounts({
+        signer: keeper.publicKey,
+        mint,
+        wsol: NATIVE_MINT,
+        signerWsol: keeperWsol,
+        config,
+        donationState,
+        matchDonationState,
+        vault,
+        oracle: priceAccount,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .postInstructions([swapIx, finalizeIx])
+      .signers([keeper])
+      .rpc();
+
+    return { matchDonationState, matchRecord, targetLamports, quotedInAmount, send };
+  }
+
+  it("Matches a donation through the pool", async () => {
+    const seed = 4;
+    const message = approvalMessage(8, charity.publicKey, matchKey.publicKey);
+
+    await donate(seed, LAMPORTS_PER_SOL / 10, [secp256r1Ix(message)], { vault, oracle: priceAccount })
+      .then(confirm)
+      .then(log);
+
+    const { matchDonationState, matchRecord, targetLamports, quotedInAmount, send } = await poolMatch(seed);
+    const preVaultBalance = new BN((await getAccount(connection, vault)).amount.toString());
+    const preMatchKeyBalance = await connection.getBalance(matchKey.publicKey);
+
+    await send()
+      .then(confirm)
+      .then(log);
+
+    // The match key receives exactly the target, paid for from the vault
+    expect(await connection.getBalance(matchKey.publicKey)).to.equal(preMatchKeyBalance + targetLamports.toNumber());
+    const postVaultBalance = new BN((await getAccount(connection, vault)).amount.toString());
+    expect(preVaultBalance.sub(postVaultBalance).toString()).to.equal(quotedInAmount.toString());
+
+    const record = await program.account.matchRecord.fetch(matchRecord);
+    expect(record.source).to.deep.equal({ swap: {} });
+    expect(record.mint.toBase58()).to.equal(mint.toBase58());
+    expect(record.amountSpent.toString()).to.equal(quotedInAmount.toString());
+    expect(await connection.getAccountInfo(matchDonationState)).to.be.null;
+  })
+
+  it("Rejects a pool that is not allowed", async () => {
+    const seed = 5;
+    const message = approvalMessage(9, charity.publicKey, matchKey.publicKey);
+
+    await donate(seed, LAMPORTS_PER_SOL / 10, [secp256r1Ix(message)], { vault, oracle: priceAccount })
+      .then(confirm)
+      .then(log);
+
+    await program.methods
+      .setRoutePolicy({ filter: { deny: {} }, amms: new BN(0), maxHops: 255, pools: [] })
+      .accounts({ admin, config })
+      .rpc()
+      .then(confirm);
+
+    const { send } = await poolMatch(seed);
+    await expectError(send(), "PoolNotAllowed");
+  })
+
+  it("Withdraws liquidity as the pool admin", async () => {
+    const [mintA, mintB] = sortedMints();
+    const poolVault = getAssociatedTokenAddressSync(mint, pool, true);
+    const before = new BN((await getAccount(connection, poolVault)).amount.toString());
+
+    const adminToken = new Map([
+      [mint.toBase58(), getAssociatedTokenAddressSync(mint, admin)],
+      [NATIVE_MINT.toBase58(), getAssociatedTokenAddressSync(NATIVE_MINT, admin)],
+    ]);
+    const amounts = new Map([[mint.toBase58(), new BN(1_000_000)], [NATIVE_MINT.toBase58(), new BN(0)]]);
+
+    await poolProgram.methods
+      .withdraw(amounts.get(mintA.toBase58()), amounts.get(mintB.toBase58()))
+      .accounts({
+        admin,
+        pool,
+        mintA,
+        mintB,
+        vaultA: getAssociatedTokenAddressSync(mintA, pool, true),
+        vaultB: getAssociatedTokenAddressSync(mintB, pool, true),
+        adminA: adminToken.get(mintA.toBase58()),
+        adminB: adminToken.get(mintB.toBase58()),
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc()
+      .then(confirm)
+      .then(log);
 
-  // it("Airdrop", async () => {
-  //   await connection.requestAirdrop(signer.publicKey, LAMPORTS_PER_SOL * 10)
-  //   .then(confirm)
-  //   .then(log)
-  // })
-  // it("ATAs", async () => {
-  //   await getOrCreateAssociatedTokenAccount(connection, { mint: bonk, owner: signer.publicKey }).
-  // })
-  // it("Is initialized!", async () => {
-  //   // Add your test here.
-  //   const tx = new Transaction();
-  //   // Set compute unit limit to 1.4mil
-  //   const setComputeUnitLImitIx = ComputeBudgetProgram.setComputeUnitLimit({
-  //     units: 1_400_000,
-  //   });
-
-  //   const donateIx = program.methods.donate(
-  //     new BN(1e6),
-  //     new BN(1000),
-  //   )
-  //   .accounts({
-  //     donor: signer.publicKey,
-  //     charity: charity.publicKey,
-  //     wsol,
-  //     donorBonk,
-  //     poolBonk,
-  //     poolWsol,
-  //     tokenProgram: TOKEN_PROGRAM_ID,
-  //     associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
-  //     systemProgram: SystemProgram.programId
-  //   })
-  //   tx.instructions.push();
-  //    await program.methods.donate().rpc();
-  //   console.log("Your transaction signature", tx);
-  // });
+    const after = new BN((await getAccount(connection, poolVault)).amount.toString());
+    expect(before.sub(after).toNumber()).to.equal(1_000_000);
+  })
 });